@@ -0,0 +1,85 @@
+//! Times `render_tape` against rendering that works out each task's color at
+//! every change along the tape, as it did before colors were cached.
+//!
+//! Run with `cargo bench --bench render`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use daytape::{render_tape, task_color, DayState, Format, Palette, Task, Time, TimeSlot};
+
+const RUNS: u32 = 2000;
+
+const COLORS: &[[u8; 3]] = &[
+    [0xe0, 0x6c, 0x75],
+    [0x98, 0xc3, 0x79],
+    [0x61, 0xaf, 0xef],
+    [0xc6, 0x78, 0xdd],
+];
+
+fn uncached(state: &DayState, from: Time, width: usize, step: usize, palette: Palette) -> String {
+    let mut to_display = String::new();
+    let mut index = None;
+    let mut label = "".chars();
+
+    let mut time = from;
+    for _ in 0..width {
+        let current_index = state.index_at(time + Time::mins(step / 2));
+        if current_index != index {
+            index = current_index;
+            label = index.map_or("", |i| &state.tasks[i].label).chars();
+            match index.map(|i| task_color(&state.tasks[i], palette)) {
+                Some([r, g, b]) => to_display.push_str(&format!("#[bg=#{r:02x}{g:02x}{b:02x}]")),
+                None => to_display.push_str("#[bg=default]"),
+            }
+        }
+        to_display.push(label.next().unwrap_or(' '));
+        time += Time::mins(step);
+    }
+
+    to_display.push_str("#[bg=default]");
+    to_display
+}
+
+fn time(name: &str, mut render: impl FnMut() -> String) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(render());
+    }
+    let elapsed = start.elapsed() / RUNS;
+    println!("{name:>8}: {elapsed:?} per render");
+    elapsed
+}
+
+fn main() {
+    // A busy day: a task every quarter hour, with a few minutes between each
+    let tasks = (0..96)
+        .map(|i| {
+            let slot = TimeSlot {
+                start: Time::mins(i * 15),
+                duration: 12,
+            };
+            Task::new(slot, format!("Task number {}", i % 7))
+        })
+        .collect();
+    let state = DayState {
+        date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+        tasks,
+    };
+    let palette = Palette {
+        colors: COLORS,
+        stable: true,
+    };
+
+    // The whole day a minute at a time, then a few hours as a status line
+    for (from, width, step) in [(0, 1440, 1), (9 * 60, 48, 5)] {
+        let from = Time::mins(from);
+        println!("{from} onwards, {width} columns of {step} minutes:");
+        let before = time("before", || uncached(&state, from, width, step, palette));
+        let after = time("after", || {
+            render_tape(&state, from, width, step, None, palette, Format::Tmux)
+        });
+        println!("{:.2}x as fast", before.as_secs_f64() / after.as_secs_f64());
+    }
+}
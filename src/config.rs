@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use chrono::{Days, NaiveDate};
+use color_eyre::eyre::{bail, Result, WrapErr};
+use serde::Deserialize;
+
+use daytape::{Palette, Time};
+
+use crate::{COLORS, COMMAND_HELP, DAY_END, DAY_START, GRID_STEP, NORMAL_KEYS, VIM_KEYS};
+
+/// Settings read from `config.toml` in the config directory. Every field is
+/// optional, and a missing file gives the defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The earliest time shown in the editor, and where the tape starts.
+    pub day_start: Time,
+
+    /// The latest time the editor cursor can reach.
+    pub day_end: Time,
+
+    /// Where `show` starts the tape, rather than at the current time.
+    pub tape_from: Option<Time>,
+
+    /// The colors given to tasks, in place of the built-in palette.
+    pub colors: Option<Vec<[u8; 3]>>,
+
+    /// The color of the editor's marker for the current time.
+    pub now_color: [u8; 3],
+
+    /// Move the editor's cursor with `h`/`j`/`k`/`l`, and only type into
+    /// tasks after pressing `i`.
+    pub vim_keys: bool,
+
+    /// Whether `show` and `edit` switch to tomorrow late in the day.
+    pub rollover: bool,
+
+    /// Once this hour has passed, `show` and `edit` default to tomorrow.
+    pub rollover_hour: usize,
+
+    /// Hash labels to colors so that adding colors to the end of the palette
+    /// keeps most labels' colors. See [`daytape::color_index`].
+    pub stable_colors: bool,
+
+    /// Give each label in a day its own color, rather than always hashing a
+    /// label to the same color.
+    pub distinct_colors: bool,
+
+    /// The minutes covered by each cell of the editor's grid, and the step
+    /// that tasks are moved and resized by.
+    pub grid_step: usize,
+
+    /// How long new tasks last, in minutes. See
+    /// [`Config::default_duration`].
+    pub default_duration: Option<usize>,
+
+    /// A shell command `watch` runs as each task starts.
+    pub on_start: Option<String>,
+
+    /// A shell command `watch` runs as each task ends.
+    pub on_end: Option<String>,
+
+    /// Keep days before today when saving from the editor, rather than
+    /// removing them.
+    pub keep_history: bool,
+
+    /// With `keep_history`, how many days before today to keep.
+    pub retention_days: Option<usize>,
+
+    /// How many backups of the schedule file to keep. 0 turns them off.
+    pub backups: usize,
+
+    /// The editor's key bindings.
+    pub keys: Keys,
+}
+
+/// The characters bound to the editor's actions. `save`, `quit` and
+/// `delete` are commands typed after `command`; the others are pressed
+/// directly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    pub save: char,
+    pub quit: char,
+    pub delete: char,
+    pub scale_up: char,
+    pub scale_down: char,
+    /// Lengthen the task up to the next task, or the end of the day.
+    pub extend: char,
+    pub command: char,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            save: 's',
+            quit: 'q',
+            delete: 'd',
+            scale_up: ']',
+            scale_down: '[',
+            extend: '}',
+            command: ':',
+        }
+    }
+}
+
+impl Keys {
+    /// Finds two actions bound to the same character, counting the keys and
+    /// commands that can't be rebound, and the vim keys if they're on.
+    fn duplicate(&self, vim_keys: bool) -> Option<(String, String, char)> {
+        let rebindable = |bindings: &[(&str, char)]| -> Vec<(String, char)> {
+            bindings
+                .iter()
+                .map(|&(name, key)| (format!("keys.{name}"), key))
+                .collect()
+        };
+
+        let mut commands = rebindable(&[
+            ("save", self.save),
+            ("quit", self.quit),
+            ("delete", self.delete),
+        ]);
+        // Only commands with one-character names can clash with a rebound one
+        commands.extend(COMMAND_HELP.iter().filter_map(|(usage, _)| {
+            let name = usage.split(' ').next()?;
+            let mut chars = name.chars();
+            let key = chars.next()?;
+            chars
+                .next()
+                .is_none()
+                .then(|| (format!("the {name} command"), key))
+        }));
+
+        let mut keys = rebindable(&[
+            ("scale_up", self.scale_up),
+            ("scale_down", self.scale_down),
+            ("extend", self.extend),
+            ("command", self.command),
+        ]);
+        keys.extend(
+            NORMAL_KEYS
+                .iter()
+                .map(|&(key, _)| (format!("the {key} key"), key)),
+        );
+        if vim_keys {
+            let vim = VIM_KEYS.iter().map(|&(key, _)| key).chain(['i']);
+            keys.extend(vim.map(|key| (format!("the vim {key} key"), key)));
+        }
+
+        [commands, keys].into_iter().find_map(|bindings| {
+            bindings.iter().enumerate().find_map(|(i, (a, key))| {
+                bindings[i + 1..]
+                    .iter()
+                    .find(|(_, other)| other == key)
+                    .map(|(b, _)| (a.clone(), b.clone(), *key))
+            })
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            day_start: DAY_START,
+            day_end: DAY_END,
+            tape_from: None,
+            colors: None,
+            now_color: [255, 90, 90],
+            vim_keys: false,
+            rollover: false,
+            rollover_hour: 18,
+            stable_colors: false,
+            distinct_colors: false,
+            grid_step: GRID_STEP,
+            default_duration: None,
+            on_start: None,
+            on_end: None,
+            keep_history: false,
+            retention_days: None,
+            backups: 10,
+            keys: Keys::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut config: Config = toml::from_str(&source)
+            .wrap_err_with(|| format!("invalid config file {}", path.display()))?;
+
+        if config.day_start > config.day_end {
+            bail!(
+                "day_start ({}) must not be later than day_end ({})",
+                config.day_start,
+                config.day_end
+            );
+        }
+
+        if config.rollover_hour > 23 {
+            bail!(
+                "rollover_hour {} is out of range (expected 0-23)",
+                config.rollover_hour
+            );
+        }
+
+        if config.grid_step == 0 || !60usize.is_multiple_of(config.grid_step) {
+            bail!(
+                "grid_step {} must divide an hour evenly (like 1, 5, 10 or 15)",
+                config.grid_step
+            );
+        }
+
+        let step = config.grid_step;
+        // Cells start on multiples of the step, so the day's first and last
+        // cells have to as well
+        config.day_start = config.day_start.floor_to(step);
+        config.day_end = config.day_end.floor_to(step);
+        if let Some(duration) = config.default_duration {
+            if duration == 0 || !duration.is_multiple_of(step) {
+                bail!(
+                    "default_duration {duration} must be a positive multiple of grid_step ({step} minutes)"
+                );
+            }
+        }
+
+        if let Some((a, b, key)) = config.keys.duplicate(config.vim_keys) {
+            bail!("{a} and {b} are both bound to '{key}'");
+        }
+
+        if config
+            .colors
+            .as_ref()
+            .is_some_and(|colors| colors.is_empty())
+        {
+            bail!("colors must contain at least one color");
+        }
+
+        Ok(config)
+    }
+
+    /// How long new tasks last: the configured duration, or 15 minutes
+    /// rounded up to the grid.
+    pub fn default_duration(&self) -> usize {
+        self.default_duration
+            .unwrap_or(15usize.div_ceil(self.grid_step) * self.grid_step)
+    }
+
+    /// The earliest date to keep in the schedule when saving, if any.
+    pub fn keep_from(&self, today: NaiveDate) -> Option<NaiveDate> {
+        match (self.keep_history, self.retention_days) {
+            (false, _) => Some(today),
+            (true, Some(days)) => today.checked_sub_days(Days::new(days as u64)),
+            (true, None) => None,
+        }
+    }
+
+    /// The configured palette, or the built-in one.
+    pub fn palette(&self) -> Palette<'_> {
+        Palette {
+            colors: self.colors.as_deref().unwrap_or(COLORS),
+            stable: self.stable_colors,
+        }
+    }
+
+    /// The first hour row shown in the editor.
+    pub fn first_hour(&self) -> usize {
+        self.day_start.hour
+    }
+
+    /// The last hour row shown in the editor.
+    pub fn last_hour(&self) -> usize {
+        self.day_end.hour
+    }
+}
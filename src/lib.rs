@@ -1,29 +1,61 @@
 use std::{
-    collections::HashMap,
-    ops::{Add, AddAssign, Sub, SubAssign},
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::{Add, AddAssign, Deref, Sub, SubAssign},
+    str::FromStr,
 };
 
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike, Weekday};
 
-#[derive(
-    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
-)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Time {
     pub hour: usize,
     pub min: usize,
+    pub sec: usize,
 }
 
+/// The longest a task can run, in minutes.
+pub const DAY_MINS: usize = 24 * 60;
+const DAY_SECS: usize = DAY_MINS * 60;
+
 impl Time {
     pub const MAX: Time = Time::new(23, 59);
 
     pub const fn new(hour: usize, min: usize) -> Self {
-        Time { hour, min }
+        Time { hour, min, sec: 0 }
+    }
+
+    pub const fn new_with_secs(hour: usize, min: usize, sec: usize) -> Self {
+        Time { hour, min, sec }
+    }
+
+    /// Like [`Time::new`], but rejects an hour or minute outside a single day.
+    pub const fn try_new(hour: usize, min: usize) -> Result<Self, ParseTimeError> {
+        Time::try_new_with_secs(hour, min, 0)
+    }
+
+    /// Like [`Time::new_with_secs`], but rejects any field outside a single day.
+    pub const fn try_new_with_secs(
+        hour: usize,
+        min: usize,
+        sec: usize,
+    ) -> Result<Self, ParseTimeError> {
+        if hour >= 24 {
+            Err(ParseTimeError::HourOutOfRange(hour))
+        } else if min >= 60 {
+            Err(ParseTimeError::MinuteOutOfRange(min))
+        } else if sec >= 60 {
+            Err(ParseTimeError::SecondOutOfRange(sec))
+        } else {
+            Ok(Time::new_with_secs(hour, min, sec))
+        }
     }
 
     pub const fn hours(hours: usize) -> Self {
         Time {
             hour: hours,
             min: 0,
+            sec: 0,
         }
     }
 
@@ -31,34 +63,298 @@ impl Time {
         Time {
             hour: mins / 60,
             min: mins % 60,
+            sec: 0,
         }
     }
 
-    pub const fn to_grid(&self) -> [usize; 2] {
+    pub const fn secs(secs: usize) -> Self {
+        Time {
+            hour: secs / 3600,
+            min: (secs / 60) % 60,
+            sec: secs % 60,
+        }
+    }
+
+    /// Maps the time to an `[x, y]` cell on a grid with one row per hour and
+    /// one column per `step_mins` minutes.
+    pub const fn to_grid(&self, step_mins: usize) -> [usize; 2] {
         let y = self.hour;
-        let x = self.min / 5;
+        let x = self.min / step_mins;
         [x, y]
     }
 
+    /// Whole minutes since midnight; seconds are truncated.
     pub const fn in_mins(&self) -> usize {
         self.hour * 60 + self.min
     }
 
-    pub fn clamp(&self, min: Time, max: Time) -> Time {
-        let mins = std::cmp::min(std::cmp::max(min.in_mins(), self.in_mins()), max.in_mins());
-        Time::mins(mins)
+    pub const fn in_secs(&self) -> usize {
+        self.in_mins() * 60 + self.sec
+    }
+
+    /// Whether the time lies in the half-open range `start..end`, the same
+    /// convention as [`TimeSlot::contains`].
+    pub fn is_between(self, start: Time, end: Time) -> bool {
+        start <= self && self < end
+    }
+
+    /// Whether the time lies in the closed range `start..=end`.
+    pub fn is_between_inclusive(self, start: Time, end: Time) -> bool {
+        start <= self && self <= end
+    }
+
+    /// Signed minutes from `other` to `self` within a single day, without
+    /// wrapping around midnight like `Sub` does.
+    pub const fn diff(self, other: Time) -> i64 {
+        (self.in_secs() as i64 - other.in_secs() as i64) / 60
+    }
+
+    /// Signed minutes from `self` until `other`; negative if `other` has
+    /// already passed.
+    pub const fn minutes_until(self, other: Time) -> i64 {
+        other.diff(self)
+    }
+
+    /// Formats the time on a 12-hour clock, e.g. `"2:30pm"`.
+    pub fn format_12h(&self) -> String {
+        let hour = match self.hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        let meridiem = if self.hour < 12 { "am" } else { "pm" };
+        match self.sec {
+            0 => format!("{hour}:{:02}{meridiem}", self.min),
+            sec => format!("{hour}:{:02}:{sec:02}{meridiem}", self.min),
+        }
+    }
+
+    /// Adds `other`, returning `None` if the result would pass midnight.
+    pub const fn checked_add(self, other: Time) -> Option<Time> {
+        match self.overflowing_add(other) {
+            (time, 0) => Some(time),
+            _ => None,
+        }
+    }
+
+    /// Adds `other`, wrapping past midnight, and returns the number of days
+    /// carried along with the result.
+    pub const fn overflowing_add(self, other: Time) -> (Time, u32) {
+        let secs = self.in_secs() + other.in_secs();
+        (Time::secs(secs % DAY_SECS), (secs / DAY_SECS) as u32)
+    }
+
+    /// Rounds to the closest multiple of `step_mins` since midnight, with
+    /// ties rounding up. Rounding past the end of the day wraps to midnight.
+    pub const fn round_to_nearest(self, step_mins: usize) -> Time {
+        let step = step_mins * 60;
+        if step == 0 {
+            return self;
+        }
+        Time::from_grid_secs((self.in_secs() + step / 2) / step * step)
+    }
+
+    /// Rounds down to a multiple of `step_mins` since midnight.
+    pub const fn floor_to(self, step_mins: usize) -> Time {
+        let step = step_mins * 60;
+        if step == 0 {
+            return self;
+        }
+        Time::from_grid_secs(self.in_secs() / step * step)
+    }
+
+    /// Rounds up to a multiple of `step_mins` since midnight. Rounding past
+    /// the end of the day wraps to midnight.
+    pub const fn ceil_to(self, step_mins: usize) -> Time {
+        let step = step_mins * 60;
+        if step == 0 {
+            return self;
+        }
+        Time::from_grid_secs(self.in_secs().div_ceil(step) * step)
+    }
+
+    /// Steps that don't divide the day evenly can land beyond midnight; snap
+    /// those to the start of the next day rather than an off-grid time.
+    const fn from_grid_secs(secs: usize) -> Time {
+        if secs >= DAY_SECS {
+            Time::new(0, 0)
+        } else {
+            Time::secs(secs)
+        }
+    }
+
+    /// Restricts the time to `min..=max`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min > max`, matching [`Ord::clamp`]. Takes `self` by value
+    /// so that method calls resolve here rather than to `Ord::clamp`.
+    pub fn clamp(self, min: Time, max: Time) -> Time {
+        assert!(min <= max, "Time::clamp called with min {min} > max {max}");
+        let secs = std::cmp::min(std::cmp::max(min.in_secs(), self.in_secs()), max.in_secs());
+        Time::secs(secs)
+    }
+}
+
+/// Formats the time on a 24-hour clock, e.g. `"14:30"`, including seconds
+/// only when they are non-zero. See [`Time::format_12h`] for the 12-hour form.
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.min)?;
+        if self.sec != 0 {
+            write!(f, ":{:02}", self.sec)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTimeError {
+    Format(String),
+    HourOutOfRange(usize),
+    Hour12OutOfRange(usize),
+    MinuteOutOfRange(usize),
+    SecondOutOfRange(usize),
+}
+
+impl fmt::Display for ParseTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseTimeError::Format(s) => {
+                write!(f, "invalid time {s:?}, expected HH:MM or H:MMam/pm")
+            }
+            ParseTimeError::HourOutOfRange(hour) => {
+                write!(f, "hour {hour} is out of range (expected 0-23)")
+            }
+            ParseTimeError::Hour12OutOfRange(hour) => {
+                write!(
+                    f,
+                    "hour {hour} is out of range (expected 1-12 before am/pm)"
+                )
+            }
+            ParseTimeError::MinuteOutOfRange(min) => {
+                write!(f, "minute {min} is out of range (expected 0-59)")
+            }
+            ParseTimeError::SecondOutOfRange(sec) => {
+                write!(f, "second {sec} is out of range (expected 0-59)")
+            }
+        }
     }
 }
 
+impl std::error::Error for ParseTimeError {}
+
+impl FromStr for Time {
+    type Err = ParseTimeError;
+
+    /// Parses 24-hour times like `"14:30"`, `"9:05"` and `"9:02:30"`, or
+    /// 12-hour times like `"2:30pm"` and `"9am"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format_err = || ParseTimeError::Format(s.to_owned());
+
+        let trimmed = s.trim().to_ascii_lowercase();
+        let (digits, meridiem) = if let Some(rest) = trimmed.strip_suffix("am") {
+            (rest.trim_end(), Some(false))
+        } else if let Some(rest) = trimmed.strip_suffix("pm") {
+            (rest.trim_end(), Some(true))
+        } else {
+            (trimmed.as_str(), None)
+        };
+
+        let parts: Vec<&str> = digits.split(':').collect();
+        let (hour, min, sec) = match parts[..] {
+            [hour, min] if min.len() == 2 => (hour, min, "00"),
+            [hour, min, sec] if min.len() == 2 && sec.len() == 2 => (hour, min, sec),
+            [hour] if meridiem.is_some() => (hour, "00", "00"),
+            _ => return Err(format_err()),
+        };
+        let parse = |part: &str| -> Result<usize, ParseTimeError> {
+            if part.is_empty() || part.len() > 2 || !part.chars().all(|c| c.is_ascii_digit()) {
+                return Err(format_err());
+            }
+            part.parse().map_err(|_| format_err())
+        };
+        let hour = parse(hour)?;
+        let min = parse(min)?;
+        let sec = parse(sec)?;
+
+        let hour = match meridiem {
+            None => hour,
+            Some(pm) if (1..=12).contains(&hour) => (hour % 12) + if pm { 12 } else { 0 },
+            Some(_) => return Err(ParseTimeError::Hour12OutOfRange(hour)),
+        };
+
+        Time::try_new_with_secs(hour, min, sec)
+    }
+}
+
+/// Serializes as the [`Display`](fmt::Display) form, e.g. `"09:00"`.
+impl serde::Serialize for Time {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from a string like `"09:00"`, or from the legacy
+/// `{hour, min}` map written by older versions (see [`LegacyTime`]).
+impl<'de> serde::Deserialize<'de> for Time {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimeVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TimeVisitor {
+            type Value = Time;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a time string like \"09:00\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Time, E> {
+                s.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Time, A::Error> {
+                LegacyTime::deserialize_legacy_map(map)
+            }
+        }
+
+        deserializer.deserialize_any(TimeVisitor)
+    }
+}
+
+/// The `{hour, min}` map form that `Time` was serialized as before it became
+/// a string. Still accepted on read so existing schedule files migrate on
+/// their next save; remove once that has had a release to settle.
+#[derive(serde::Deserialize)]
+struct LegacyTime {
+    hour: usize,
+    min: usize,
+    #[serde(default)]
+    sec: usize,
+}
+
+impl LegacyTime {
+    fn deserialize_legacy_map<'de, A: serde::de::MapAccess<'de>>(map: A) -> Result<Time, A::Error> {
+        use serde::{de::Error, Deserialize};
+
+        let raw = LegacyTime::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+        Time::try_new_with_secs(raw.hour, raw.min, raw.sec).map_err(A::Error::custom)
+    }
+}
+
+/// Wrapping addition: the result rolls over past midnight without any
+/// indication that a day boundary was crossed. Use [`Time::checked_add`] or
+/// [`Time::overflowing_add`] when that matters.
 impl Add<Time> for Time {
     type Output = Time;
 
     fn add(self, other: Time) -> Self::Output {
-        let min = self.min + other.min;
+        let sec = self.sec + other.sec;
+        let plus_mins = sec / 60;
+        let sec = sec % 60;
+        let min = self.min + other.min + plus_mins;
         let plus_hours = min / 60;
         let hour = (self.hour + other.hour + plus_hours) % 24;
         let min = min % 60;
-        Time { hour, min }
+        Time { hour, min, sec }
     }
 }
 
@@ -72,15 +368,21 @@ impl Sub<Time> for Time {
     type Output = Time;
 
     fn sub(self, other: Time) -> Self::Output {
-        let (min, minus_hours) = if self.min >= other.min {
-            (self.min - other.min, 0)
+        let (sec, minus_mins) = if self.sec >= other.sec {
+            (self.sec - other.sec, 0)
+        } else {
+            ((self.sec + 60) - other.sec, 1)
+        };
+
+        let (min, minus_hours) = if self.min >= other.min + minus_mins {
+            (self.min - other.min - minus_mins, 0)
         } else {
-            ((self.min + 60) - other.min, 1)
+            ((self.min + 60) - other.min - minus_mins, 1)
         };
 
         let hour = ((self.hour + 24) - other.hour - minus_hours) % 24;
 
-        Time { hour, min }
+        Time { hour, min, sec }
     }
 }
 
@@ -99,33 +401,1584 @@ pub struct TimeSlot {
 }
 
 impl TimeSlot {
+    /// The slot from `start` until `end`, where an `end` at or before `start`
+    /// is taken to be on the following day, so the slot spans midnight.
+    pub fn until(start: Time, end: Time) -> TimeSlot {
+        let duration = (end.in_mins() + DAY_MINS - start.in_mins()) % DAY_MINS;
+        TimeSlot {
+            start,
+            duration: if duration == 0 { DAY_MINS } else { duration },
+        }
+    }
+
+    /// The clock time at which the slot ends. For a slot that runs until or
+    /// past midnight, this is a time on the following day, before `start`.
     pub fn end(&self) -> Time {
-        let end = self.start + Time::mins(self.duration);
-        if end < self.start {
+        self.start + Time::mins(self.duration)
+    }
+
+    /// Like [`TimeSlot::end`], but a slot that runs until or past midnight
+    /// ends at [`Time::MAX`] instead of wrapping into the next day.
+    pub fn end_within_day(&self) -> Time {
+        if self.end_mins() >= DAY_MINS {
             Time::MAX
         } else {
-            end
+            self.end()
         }
     }
 
+    /// Whether the slot continues past midnight into the following day.
+    pub fn spans_midnight(&self) -> bool {
+        self.end_mins() > DAY_MINS
+    }
+
+    /// The part of the slot after midnight, as a slot starting at midnight on
+    /// the following day, if the slot [spans midnight](TimeSlot::spans_midnight).
+    pub fn overflow(&self) -> Option<TimeSlot> {
+        self.spans_midnight().then(|| TimeSlot {
+            start: Time::new(0, 0),
+            duration: self.end_mins() - DAY_MINS,
+        })
+    }
+
+    /// Whether `time` falls within the slot on the slot's own day. A slot that
+    /// runs past midnight contains the times from its start until midnight;
+    /// the rest is its [overflow](TimeSlot::overflow) on the next day.
     pub fn contains(&self, time: Time) -> bool {
-        self.start <= time && time < self.end()
+        if self.end_mins() < DAY_MINS {
+            time.is_between(self.start, self.end())
+        } else {
+            time >= self.start
+        }
+    }
+
+    /// Minutes from `time` until the slot ends, for a `time` the slot
+    /// [contains](TimeSlot::contains).
+    pub fn minutes_left(&self, time: Time) -> usize {
+        (self.end_mins() + DAY_MINS - time.in_mins()) % DAY_MINS
     }
+
+    /// Minutes since midnight at which the slot ends, without wrapping.
+    fn end_mins(&self) -> usize {
+        self.start.in_mins() + self.duration
+    }
+
+    /// Whether the two slots share any time. Slots that merely touch, with one
+    /// ending exactly where the other starts, do not overlap.
+    pub fn overlaps(&self, other: &TimeSlot) -> bool {
+        self.start.in_mins() < other.end_mins() && other.start.in_mins() < self.end_mins()
+    }
+
+    /// The time shared by both slots, or `None` if they don't
+    /// [overlap](TimeSlot::overlaps).
+    pub fn intersection(&self, other: &TimeSlot) -> Option<TimeSlot> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        let start = std::cmp::max(self.start, other.start);
+        let end_mins = std::cmp::min(self.end_mins(), other.end_mins());
+        Some(TimeSlot {
+            start,
+            duration: end_mins - start.in_mins(),
+        })
+    }
+
+    /// The union of the two slots if they [overlap](TimeSlot::overlaps) or
+    /// touch end-to-start, or `None` if there is a gap between them.
+    pub fn merge(&self, other: &TimeSlot) -> Option<TimeSlot> {
+        if self.start.in_mins() > other.end_mins() || other.start.in_mins() > self.end_mins() {
+            return None;
+        }
+        let start = std::cmp::min(self.start, other.start);
+        let end_mins = std::cmp::max(self.end_mins(), other.end_mins());
+        Some(TimeSlot {
+            start,
+            duration: end_mins - start.in_mins(),
+        })
+    }
+
+    /// Cuts the slot in two at `at`, or returns `None` unless `at` lies
+    /// strictly inside the slot.
+    pub fn split_at(&self, at: Time) -> Option<(TimeSlot, TimeSlot)> {
+        if !self.contains(at) {
+            return None;
+        }
+        let offset = (at.in_mins() + DAY_MINS - self.start.in_mins()) % DAY_MINS;
+        if offset == 0 {
+            return None;
+        }
+        let first = TimeSlot {
+            start: self.start,
+            duration: offset,
+        };
+        let second = TimeSlot {
+            start: at,
+            duration: self.duration - offset,
+        };
+        Some((first, second))
+    }
+}
+
+/// Coalesces slots that overlap or touch into as few slots as possible,
+/// returned in start order.
+pub fn merge_slots(slots: &[TimeSlot]) -> Vec<TimeSlot> {
+    let mut sorted = slots.to_vec();
+    sorted.sort();
+
+    let mut merged: Vec<TimeSlot> = Vec::with_capacity(sorted.len());
+    for slot in sorted {
+        let union = merged.last().and_then(|last| last.merge(&slot));
+        match union {
+            Some(union) => *merged.last_mut().unwrap() = union,
+            None => merged.push(slot),
+        }
+    }
+    merged
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 pub struct Task {
     pub slot: TimeSlot,
     pub label: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub done: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// An RGB color to use instead of the one derived from
+    /// [`Task::color_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+    /// Longer free-form details, shown in the editor but not on the tape.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl Task {
+    pub fn new(slot: TimeSlot, label: impl Into<String>) -> Self {
+        Task {
+            slot,
+            label: label.into(),
+            done: false,
+            tags: vec![],
+            color: None,
+            notes: None,
+        }
+    }
+
+    pub fn toggle_done(&mut self) {
+        self.done = !self.done;
+    }
+
+    /// Applies [`sanitize_label`] to the task's label, returning the old
+    /// label if it changed.
+    pub fn sanitize_label(&mut self) -> Option<String> {
+        let label = sanitize_label(&self.label);
+        (label != self.label).then(|| std::mem::replace(&mut self.label, label))
+    }
+
+    /// Whether the task has `tag`, ignoring any leading `#` on either side.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim_start_matches('#');
+        self.tags.iter().any(|t| t.trim_start_matches('#') == tag)
+    }
+
+    /// The string the task's color is derived from: its first tag, so that
+    /// related tasks share a color, or otherwise its label.
+    pub fn color_key(&self) -> &str {
+        match self.tags.first() {
+            Some(tag) => tag.trim_start_matches('#'),
+            None => &self.label,
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DayState {
     pub date: NaiveDate,
+    #[serde(serialize_with = "serialize_sorted_tasks")]
     pub tasks: Vec<Task>,
 }
 
+impl DayState {
+    /// A copy of the day's tasks for another date, none of them done yet.
+    pub fn copy_to(&self, date: NaiveDate) -> DayState {
+        let mut tasks = self.tasks.clone();
+        for task in &mut tasks {
+            task.done = false;
+        }
+        DayState { date, tasks }
+    }
+
+    /// The first task whose slot contains `time`.
+    pub fn task_at(&self, time: Time) -> Option<&Task> {
+        self.index_at(time).map(|i| &self.tasks[i])
+    }
+
+    /// The index in `tasks` of the task happening at `time`.
+    pub fn index_at(&self, time: Time) -> Option<usize> {
+        self.tasks.iter().position(|task| task.slot.contains(time))
+    }
+
+    pub fn task_at_mut(&mut self, time: Time) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.slot.contains(time))
+    }
+
+    /// The free intervals within `within` not covered by any task, in start
+    /// order. Overlapping tasks are treated as one busy block.
+    pub fn gaps(&self, within: TimeSlot) -> Vec<TimeSlot> {
+        let slots: Vec<TimeSlot> = self.tasks.iter().map(|task| task.slot).collect();
+        let window_end = within.end_mins();
+
+        let mut gaps = vec![];
+        let mut free_from = within.start.in_mins();
+        for busy in merge_slots(&slots) {
+            let busy_start = std::cmp::min(busy.start.in_mins(), window_end);
+            if busy_start > free_from {
+                gaps.push(TimeSlot {
+                    start: Time::mins(free_from % DAY_MINS),
+                    duration: busy_start - free_from,
+                });
+            }
+            free_from = std::cmp::max(free_from, busy.end_mins());
+            if free_from >= window_end {
+                return gaps;
+            }
+        }
+        if free_from < window_end {
+            gaps.push(TimeSlot {
+                start: Time::mins(free_from % DAY_MINS),
+                duration: window_end - free_from,
+            });
+        }
+        gaps
+    }
+
+    /// Adds each of `tasks` that doesn't overlap a task already in the day,
+    /// returning how many were added.
+    pub fn merge_tasks(&mut self, tasks: &[Task]) -> usize {
+        let mut added = 0;
+        for task in tasks {
+            if !self.tasks.iter().any(|t| t.slot.overlaps(&task.slot)) {
+                self.tasks.push(task.clone());
+                added += 1;
+            }
+        }
+        self.tasks.sort();
+        added
+    }
+
+    /// Total minutes covered by tasks, counting double-booked time once.
+    pub fn total_scheduled(&self) -> usize {
+        let slots: Vec<TimeSlot> = self.tasks.iter().map(|task| task.slot).collect();
+        merge_slots(&slots).iter().map(|slot| slot.duration).sum()
+    }
+
+    /// Minutes within `within` not covered by any task.
+    pub fn free_minutes(&self, within: TimeSlot) -> usize {
+        self.gaps(within).iter().map(|slot| slot.duration).sum()
+    }
+
+    /// Whether any two tasks [overlap](TimeSlot::overlaps).
+    pub fn has_conflicts(&self) -> bool {
+        let mut slots: Vec<TimeSlot> = self.tasks.iter().map(|task| task.slot).collect();
+        slots.sort();
+
+        let mut busy_until = 0;
+        for slot in slots.iter().filter(|slot| slot.duration > 0) {
+            if slot.start.in_mins() < busy_until {
+                return true;
+            }
+            busy_until = std::cmp::max(busy_until, slot.end_mins());
+        }
+        false
+    }
+
+    /// Removes every overlap by cutting short the earlier of each two
+    /// overlapping tasks, or moving the longer one to start after the other
+    /// when both start together. Exact duplicates are dropped. Tasks starting
+    /// together with one that runs until midnight are left overlapping, as
+    /// there's no room after it. Returns how many overlaps were fixed.
+    pub fn resolve_conflicts(&mut self) -> usize {
+        self.tasks.sort();
+        let mut fixed = 0;
+        let mut i = 0;
+        while i + 1 < self.tasks.len() {
+            let [a, b] = [self.tasks[i].slot, self.tasks[i + 1].slot];
+            if !a.overlaps(&b) {
+                i += 1;
+                continue;
+            }
+            if b.start > a.start {
+                self.tasks[i].slot.duration = a.start.minutes_until(b.start) as usize;
+                i += 1;
+            } else if self.tasks[i] == self.tasks[i + 1] {
+                self.tasks.remove(i + 1);
+            } else if a.end_mins() < DAY_MINS {
+                // Sorting puts the shorter task first, so it keeps its place
+                self.tasks[i + 1].slot.start = a.end();
+                self.tasks.sort();
+            } else {
+                i += 1;
+                continue;
+            }
+            fixed += 1;
+        }
+        fixed
+    }
+
+    /// Index pairs `(a, b)` with `a < b` of every two tasks that
+    /// [overlap](TimeSlot::overlaps), in ascending order.
+    pub fn conflicts(&self) -> Vec<(usize, usize)> {
+        let mut order: Vec<usize> = (0..self.tasks.len()).collect();
+        order.sort_by_key(|&i| self.tasks[i].slot);
+
+        let mut conflicts = vec![];
+        let mut active: Vec<usize> = vec![];
+        for i in order {
+            let slot = &self.tasks[i].slot;
+            active.retain(|&a| self.tasks[a].slot.end_mins() > slot.start.in_mins());
+            for &a in &active {
+                if slot.overlaps(&self.tasks[a].slot) {
+                    conflicts.push((std::cmp::min(a, i), std::cmp::max(a, i)));
+                }
+            }
+            active.push(i);
+        }
+        conflicts.sort();
+        conflicts
+    }
+
+    /// Every two tasks that overlap, like [`DayState::conflicts`], followed
+    /// by each of `carried` that overlaps one of the day's tasks, where
+    /// `carried` are the tasks [carried over](Schedule::carried_over) into it.
+    pub fn conflicts_with<'a>(&'a self, carried: &'a [Task]) -> Vec<(&'a Task, &'a Task)> {
+        let mut conflicts: Vec<(&Task, &Task)> = self
+            .conflicts()
+            .into_iter()
+            .map(|(a, b)| (&self.tasks[a], &self.tasks[b]))
+            .collect();
+        for other in carried {
+            for task in &self.tasks {
+                if other.slot.overlaps(&task.slot) {
+                    conflicts.push((other, task));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Replaces tabs and line breaks in a label with spaces and removes any other
+/// control characters, which would break the tape's layout.
+pub fn sanitize_label(label: &str) -> String {
+    label
+        .chars()
+        .filter_map(|ch| match ch {
+            '\t' | '\n' | '\r' => Some(' '),
+            ch if ch.is_control() => None,
+            ch => Some(ch),
+        })
+        .collect()
+}
+
+/// 64-bit FNV-1a: small, fast, and stable across runs and platforms, so it's
+/// safe to persist or derive colors from.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The color of tasks that are done, whatever their own color.
+pub const DONE_COLOR: [u8; 3] = [90, 90, 90];
+
+/// The colors given to tasks, and how labels are hashed to them.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette<'a> {
+    pub colors: &'a [[u8; 3]],
+
+    /// Hash labels so that adding colors to the end of the palette only
+    /// recolors the labels that move to the new colors. See [`color_index`].
+    pub stable: bool,
+}
+
+impl Deref for Palette<'_> {
+    type Target = [[u8; 3]];
+
+    fn deref(&self) -> &Self::Target {
+        self.colors
+    }
+}
+
+/// The index of the color in `palette` that `key` hashes to.
+///
+/// By default this is the hash modulo the palette's length, so changing the
+/// length recolors nearly every label. A stable palette uses jump consistent
+/// hashing instead: growing the palette from `n` to `n + 1` colors moves only
+/// about `1 / (n + 1)` of the labels, all of them to the new color. The
+/// labels are spread as evenly either way, but the two give different colors,
+/// and removing or reordering colors still recolors labels in both.
+pub fn color_index(key: &str, palette: Palette) -> usize {
+    let hash = fnv1a(key.as_bytes());
+    if !palette.stable {
+        return (hash % palette.len() as u64) as usize;
+    }
+
+    // From "A Fast, Minimal Memory, Consistent Hash Algorithm" (Lamping and
+    // Veach, 2014)
+    let mut key = hash;
+    let [mut bucket, mut next] = [0, 0];
+    while next < palette.len() {
+        bucket = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as usize;
+    }
+    bucket
+}
+
+/// The color a task is drawn in: [`DONE_COLOR`] once it's done, otherwise
+/// its own color or the color its [`Task::color_key`] hashes to.
+pub fn task_color(task: &Task, palette: Palette) -> [u8; 3] {
+    if task.done {
+        return DONE_COLOR;
+    }
+    task.color
+        .unwrap_or_else(|| palette[color_index(task.color_key(), palette)])
+}
+
+/// How [`render_tape`] colors the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// No colors, only the labels
+    Text,
+    /// Terminal escape codes
+    Ansi,
+    /// `#[bg=...]` styles for the tmux status line
+    Tmux,
+}
+
+/// Renders the day from `from` onwards as `width` characters, each covering
+/// `step` minutes and showing the task at its midpoint, with a `|` in the
+/// character covering `now` if given. Each task is colored from `palette`
+/// as [`task_color`] gives. The tape is blank from midnight onwards.
+pub fn render_tape(
+    state: &DayState,
+    from: Time,
+    width: usize,
+    step: usize,
+    now: Option<Time>,
+    palette: Palette,
+    format: Format,
+) -> String {
+    // The markup to emit whenever the task under the tape changes
+    let paint = |task: Option<&Task>| match (format, task.map(|task| task_color(task, palette))) {
+        (Format::Text, _) => String::new(),
+        (Format::Ansi, Some([r, g, b])) => format!("\x1b[48;2;{r};{g};{b}m"),
+        (Format::Ansi, None) => "\x1b[0m".to_owned(),
+        (Format::Tmux, Some([r, g, b])) => format!("#[bg=#{r:02x}{g:02x}{b:02x}]"),
+        (Format::Tmux, None) => "#[bg=default]".to_owned(),
+    };
+
+    // Each task's markup is worked out the first time the tape reaches it,
+    // rather than at every change along the tape
+    let mut painted: Vec<Option<String>> = vec![None; state.tasks.len()];
+    let unpainted = paint(None);
+
+    let mut to_display = String::new();
+
+    // Runs are tracked by index, so that separate tasks which happen to be
+    // equal still start their own labels
+    let mut index = None;
+    let mut label = "".chars();
+
+    // Counted in minutes, as adding to a `Time` would wrap around to the
+    // morning of the same day
+    let mut mins = from.in_mins();
+    for _ in 0..width {
+        let midpoint = mins + step / 2;
+        let current_index = match midpoint < DAY_MINS {
+            true => state.index_at(Time::mins(midpoint)),
+            false => None,
+        };
+        if current_index != index {
+            index = current_index;
+            label = index.map_or("", |i| &state.tasks[i].label).chars();
+            to_display.push_str(match index {
+                Some(i) => painted[i].get_or_insert_with(|| paint(Some(&state.tasks[i]))),
+                None => &unpainted,
+            });
+        }
+
+        let ch = label.next().unwrap_or(' ');
+        let is_now = now.is_some_and(|now| mins <= now.in_mins() && now.in_mins() < mins + step);
+        to_display.push(if is_now { '|' } else { ch });
+
+        mins += step;
+    }
+
+    to_display.push_str(&unpainted);
+    to_display
+}
+
+fn ical_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Appends a content line, folded so no physical line exceeds 75 octets.
+fn ical_fold(out: &mut String, line: &str) {
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn ical_unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match (ch, chars.clone().next()) {
+            ('\\', Some('n' | 'N')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some(next)) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            (ch, _) => unescaped.push(ch),
+        }
+    }
+    unescaped
+}
+
+/// Parses a DATE-TIME value, converting UTC times to local time. Times with a
+/// `TZID` are taken as-is.
+fn ical_datetime(value: &str) -> Option<NaiveDateTime> {
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            Some(utc.and_utc().with_timezone(&chrono::Local).naive_local())
+        }
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok(),
+    }
+}
+
+/// Parses a DURATION value like `PT1H30M`.
+fn ical_duration(value: &str) -> Option<chrono::Duration> {
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut total_secs: i64 = 0;
+    let mut number = String::new();
+    for ch in value.chars() {
+        let unit_secs = match ch {
+            '0'..='9' => {
+                number.push(ch);
+                continue;
+            }
+            'T' => continue,
+            'W' => 7 * 24 * 60 * 60,
+            'D' => 24 * 60 * 60,
+            'H' => 60 * 60,
+            'M' => 60,
+            'S' => 1,
+            _ => return None,
+        };
+        // Durations too long to represent are as invalid as malformed ones
+        let amount = number.parse::<i64>().ok()?.checked_mul(unit_secs)?;
+        total_secs = total_secs.checked_add(amount)?;
+        number.clear();
+    }
+    Some(chrono::Duration::milliseconds(
+        total_secs.checked_mul(1000)?,
+    ))
+}
+
+/// Rounds to the nearest `step_mins` grid line, moving to the next date if
+/// that rounds past midnight.
+fn snap_to_grid(datetime: NaiveDateTime, step_mins: usize) -> NaiveDateTime {
+    let [hour, min, sec] = [datetime.hour(), datetime.minute(), datetime.second()];
+    let time = Time::new_with_secs(hour as usize, min as usize, sec as usize);
+    let rounded = time.round_to_nearest(step_mins);
+    let date = match rounded.in_secs() == 0 && time.in_secs() != 0 {
+        true => datetime.date().succ_opt().unwrap(),
+        false => datetime.date(),
+    };
+    let [hour, min, sec] = [rounded.hour, rounded.min, rounded.sec].map(|n| n as u32);
+    date.and_hms_opt(hour, min, sec).unwrap()
+}
+
+/// Converts the properties of one VEVENT to a task on its start date, or
+/// explains why it can't be.
+fn ical_event(
+    props: &[(String, String, String)],
+    step_mins: usize,
+) -> Result<(NaiveDate, Task), String> {
+    let prop = |name: &str| {
+        props
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, params, value)| (params.as_str(), value.as_str()))
+    };
+    let label = prop("SUMMARY")
+        .map(|(_, v)| ical_unescape(v))
+        .unwrap_or_default();
+
+    let (params, value) = prop("DTSTART").ok_or(format!("{label:?} has no start"))?;
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return Err(format!("{label:?} is an all-day event"));
+    }
+    let start = ical_datetime(value).ok_or(format!("{label:?} has an invalid start"))?;
+    let end = match (prop("DTEND"), prop("DURATION")) {
+        (Some((_, value)), _) => ical_datetime(value),
+        (None, Some((_, value))) => {
+            ical_duration(value).and_then(|duration| start.checked_add_signed(duration))
+        }
+        (None, None) => None,
+    };
+    let end = end.ok_or(format!("{label:?} has no valid end"))?;
+    if end <= start {
+        return Err(format!("{label:?} ends before it starts"));
+    }
+    if end - start >= chrono::Duration::days(1) {
+        return Err(format!("{label:?} spans multiple days"));
+    }
+
+    let start = snap_to_grid(start, step_mins);
+    let end = snap_to_grid(end, step_mins);
+    let duration = std::cmp::max((end - start).num_minutes() as usize, step_mins);
+    let slot = TimeSlot {
+        start: Time::new(start.hour() as usize, start.minute() as usize),
+        duration,
+    };
+
+    let mut task = Task::new(slot, label);
+    task.notes = prop("DESCRIPTION").map(|(_, v)| ical_unescape(v));
+    if let Some((_, categories)) = prop("CATEGORIES") {
+        task.tags = categories.split(',').map(ical_unescape).collect();
+    }
+    Ok((start.date(), task))
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Splits one CSV line into its fields, unquoting quoted ones. `None` if a
+/// quote is left open.
+fn csv_fields(line: &str) -> Option<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (quoted, ch) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, ch) => field.push(ch),
+        }
+    }
+    fields.push(field);
+    (!quoted).then_some(fields)
+}
+
+/// Converts one CSV row to a task on its date, given the index of each
+/// `date,start,duration,label` column.
+fn csv_task(
+    fields: &[String],
+    columns: [usize; 4],
+    step_mins: usize,
+) -> Result<(NaiveDate, Task), String> {
+    let [date, start, duration, label] =
+        columns.map(|i| fields.get(i).map(|field| field.trim()).unwrap_or(""));
+    let date: NaiveDate = date.parse().map_err(|_| format!("invalid date {date:?}"))?;
+    let start: Time = start.parse().map_err(|e| format!("{e}"))?;
+    let duration = match duration.parse::<usize>() {
+        Ok(minutes) if minutes > DAY_MINS => {
+            return Err(format!("duration {minutes} is longer than a day"))
+        }
+        Ok(minutes) if minutes > 0 => minutes,
+        _ => return Err(format!("invalid duration {duration:?}")),
+    };
+    let slot = TimeSlot {
+        start: start.round_to_nearest(step_mins),
+        duration: ((duration + step_mins / 2) / step_mins).max(1) * step_mins,
+    };
+    Ok((date, Task::new(slot, label)))
+}
+
+/// Backslash-escapes the characters that would otherwise format a Markdown
+/// list item.
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// The outcome of importing tasks from another format.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Why each entry that wasn't imported was skipped.
+    pub skipped: Vec<String>,
+    /// How each label that had control characters was changed.
+    pub sanitized: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcalError(pub String);
+
+impl fmt::Display for IcalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid iCalendar data: {}", self.0)
+    }
+}
+
+impl std::error::Error for IcalError {}
+
+/// How [`Schedule::stats`] groups the time spent on tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Label,
+    /// Tasks count towards each of their tags, or `untagged` if they have
+    /// none.
+    Tag,
+}
+
+/// The minutes scheduled on one day.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DayTotal {
+    pub date: NaiveDate,
+    pub minutes: usize,
+}
+
+/// The minutes spent on one label or tag.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct GroupTotal {
+    pub name: String,
+    pub minutes: usize,
+}
+
+/// A summary of the time scheduled over a range of days.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Stats {
+    /// Minutes covered by at least one task, so overlaps count once.
+    pub total_minutes: usize,
+    pub task_count: usize,
+    pub busiest_day: Option<DayTotal>,
+    /// Minutes per label or tag, largest first.
+    pub breakdown: Vec<GroupTotal>,
+}
+
+/// A task that repeats on every date falling on one of `weekdays`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RecurringTask {
+    pub slot: TimeSlot,
+    pub label: String,
+    pub weekdays: Vec<Weekday>,
+}
+
+impl RecurringTask {
+    pub fn occurs_on(&self, date: NaiveDate) -> bool {
+        self.weekdays.contains(&date.weekday())
+    }
+}
+
+// The maps in a schedule are written out in a fixed order, so that saving an
+// unchanged schedule doesn't change the file.
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Schedule {
-    pub dates: HashMap<NaiveDate, DayState>,
+    pub dates: BTreeMap<NaiveDate, DayState>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recurring: Vec<RecurringTask>,
+    /// Named sets of tasks that can stand in for, or be copied into, a day.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    pub templates: HashMap<String, Vec<Task>>,
+    /// The name of the template used for each weekday with no explicit day.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_weekdays"
+    )]
+    pub weekday_templates: HashMap<Weekday, String>,
+}
+
+fn serialize_sorted<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: serde::Serialize + Ord,
+    V: serde::Serialize,
+    S: serde::Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+fn serialize_weekdays<S>(map: &HashMap<Weekday, String>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(weekday, _)| weekday.num_days_from_monday());
+    serializer.collect_map(entries)
+}
+
+fn serialize_sorted_tasks<S>(tasks: &[Task], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut sorted: Vec<&Task> = tasks.iter().collect();
+    sorted.sort();
+    serializer.collect_seq(sorted)
+}
+
+impl Schedule {
+    /// Renders the explicitly scheduled days from `from` to `to` inclusive as
+    /// an iCalendar (RFC 5545) document, with one event per task.
+    pub fn to_ical(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            "PRODID:-//mistodon//daytape//EN".to_owned(),
+        ];
+        let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+
+        for (date, state) in self.range(from, to) {
+            for task in &state.tasks {
+                let start = date.and_hms_opt(0, 0, 0).unwrap()
+                    + chrono::Duration::seconds(task.slot.start.in_secs() as i64);
+                let end = start + chrono::Duration::minutes(task.slot.duration as i64);
+                let uid = fnv1a(format!("{date}|{}|{}", task.label, task.slot.start).as_bytes());
+
+                lines.push("BEGIN:VEVENT".to_owned());
+                lines.push(format!("UID:{uid:016x}@daytape"));
+                lines.push(format!("DTSTAMP:{stamp}"));
+                lines.push(format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")));
+                lines.push(format!("DTEND:{}", end.format("%Y%m%dT%H%M%S")));
+                lines.push(format!("SUMMARY:{}", ical_escape(&task.label)));
+                if let Some(notes) = &task.notes {
+                    lines.push(format!("DESCRIPTION:{}", ical_escape(notes)));
+                }
+                if !task.tags.is_empty() {
+                    let tags: Vec<String> = task.tags.iter().map(|t| ical_escape(t)).collect();
+                    lines.push(format!("CATEGORIES:{}", tags.join(",")));
+                }
+                lines.push("END:VEVENT".to_owned());
+            }
+        }
+        lines.push("END:VCALENDAR".to_owned());
+
+        let mut ical = String::new();
+        for line in lines {
+            ical_fold(&mut ical, &line);
+        }
+        ical
+    }
+
+    /// Adds a task for each event in the iCalendar document `ics`, snapping
+    /// times to a `step_mins` grid. Events that can't be represented, such as
+    /// all-day or multi-day events, or that are already scheduled, are
+    /// skipped rather than failing the whole import.
+    pub fn import_ical(&mut self, ics: &str, step_mins: usize) -> Result<ImportReport, IcalError> {
+        let unfolded = ics
+            .replace("\r\n", "\n")
+            .replace("\n ", "")
+            .replace("\n\t", "");
+        if !unfolded
+            .lines()
+            .any(|line| line.trim_end() == "BEGIN:VCALENDAR")
+        {
+            return Err(IcalError("missing BEGIN:VCALENDAR".to_owned()));
+        }
+
+        let mut report = ImportReport::default();
+        let mut event: Option<Vec<(String, String, String)>> = None;
+        for line in unfolded
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| !l.is_empty())
+        {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| IcalError(format!("malformed line {line:?}")))?;
+            let (name, params) = key.split_once(';').unwrap_or((key, ""));
+            let name = name.to_ascii_uppercase();
+
+            match (name.as_str(), value) {
+                ("BEGIN", "VEVENT") if event.is_some() => {
+                    return Err(IcalError("nested VEVENT".to_owned()))
+                }
+                ("BEGIN", "VEVENT") => event = Some(vec![]),
+                ("END", "VEVENT") => {
+                    let props = event
+                        .take()
+                        .ok_or_else(|| IcalError("END:VEVENT without BEGIN".to_owned()))?;
+                    match ical_event(&props, step_mins) {
+                        Ok((date, mut task)) => {
+                            if let Some(old) = task.sanitize_label() {
+                                report
+                                    .sanitized
+                                    .push(format!("{old:?} became {:?}", task.label));
+                            }
+                            let state = self.dates.entry(date).or_insert_with(|| DayState {
+                                date,
+                                tasks: vec![],
+                            });
+                            if state.tasks.contains(&task) {
+                                report
+                                    .skipped
+                                    .push(format!("{:?} is already scheduled", task.label));
+                                continue;
+                            }
+                            state.tasks.push(task);
+                            state.tasks.sort();
+                            report.imported += 1;
+                        }
+                        Err(reason) => report.skipped.push(reason),
+                    }
+                }
+                _ => {
+                    if let Some(props) = &mut event {
+                        props.push((name, params.to_owned(), value.to_owned()));
+                    }
+                }
+            }
+        }
+        if event.is_some() {
+            return Err(IcalError("unterminated VEVENT".to_owned()));
+        }
+        Ok(report)
+    }
+
+    /// Adds a task for each `date,start,duration,label` row of `csv`, snapping
+    /// times to a `step_mins` grid. A header row naming the columns may put
+    /// them in any order, and extra columns such as `end` are ignored, so
+    /// that [`Schedule::to_csv`]'s output can be imported again. Malformed
+    /// rows, and tasks that are already scheduled, are skipped.
+    pub fn import_csv(&mut self, csv: &str, step_mins: usize) -> ImportReport {
+        let mut report = ImportReport::default();
+        let mut rows = csv
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+
+        let mut columns = [0, 1, 2, 3];
+        let header = rows.peek().and_then(|(_, line)| csv_fields(line));
+        if let Some(header) = header {
+            let names: Vec<String> = header.iter().map(|f| f.trim().to_lowercase()).collect();
+            let find = |name: &str| names.iter().position(|n| n == name);
+            if let [Some(date), Some(start), Some(duration), Some(label)] =
+                ["date", "start", "duration", "label"].map(find)
+            {
+                columns = [date, start, duration, label];
+                rows.next();
+            }
+        }
+
+        for (i, line) in rows {
+            let parsed = csv_fields(line)
+                .ok_or_else(|| "unterminated quote".to_owned())
+                .and_then(|fields| csv_task(&fields, columns, step_mins));
+            let (date, mut task) = match parsed {
+                Ok(parsed) => parsed,
+                Err(reason) => {
+                    report.skipped.push(format!("line {}: {reason}", i + 1));
+                    continue;
+                }
+            };
+            if let Some(old) = task.sanitize_label() {
+                report
+                    .sanitized
+                    .push(format!("line {}: {old:?} became {:?}", i + 1, task.label));
+            }
+            let state = self.dates.entry(date).or_insert_with(|| DayState {
+                date,
+                tasks: vec![],
+            });
+            if state.tasks.contains(&task) {
+                report.skipped.push(format!(
+                    "line {}: {:?} is already scheduled",
+                    i + 1,
+                    task.label
+                ));
+                continue;
+            }
+            state.tasks.push(task);
+            state.tasks.sort();
+            report.imported += 1;
+        }
+        report
+    }
+
+    /// Renders the explicitly scheduled days from `from` to `to` inclusive as
+    /// CSV, with a header row and one `date,start,end,duration,label` row per
+    /// task. Durations are in minutes.
+    pub fn to_csv(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let mut csv = String::from("date,start,end,duration,label\n");
+        for (date, state) in self.range(from, to) {
+            for task in &state.tasks {
+                let slot = &task.slot;
+                let label = csv_escape(&task.label);
+                csv.push_str(&format!(
+                    "{date},{},{},{},{label}\n",
+                    slot.start,
+                    slot.end(),
+                    slot.duration
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Renders the explicitly scheduled days from `from` to `to` inclusive as
+    /// Markdown, with a heading per day and a task list item per task.
+    pub fn to_markdown(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let mut sections = vec![];
+        for (date, state) in self.range(from, to) {
+            let mut section = format!("## {date}\n\n");
+            for task in &state.tasks {
+                let check = if task.done { 'x' } else { ' ' };
+                section.push_str(&format!(
+                    "- [{check}] {}\u{2013}{} **{}**\n",
+                    task.slot.start,
+                    task.slot.end(),
+                    markdown_escape(&task.label)
+                ));
+            }
+            sections.push(section);
+        }
+        sections.join("\n")
+    }
+
+    /// Removes the days before `date`, returning how many were removed.
+    pub fn prune_before(&mut self, date: NaiveDate) -> usize {
+        let kept = self.dates.split_off(&date);
+        std::mem::replace(&mut self.dates, kept).len()
+    }
+
+    /// The explicitly scheduled days from `from` to `to` inclusive, in date
+    /// order.
+    pub fn range(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Iterator<Item = (&NaiveDate, &DayState)> {
+        self.dates
+            .range(from..)
+            .take_while(move |(date, _)| **date <= to)
+    }
+
+    /// Summarizes the explicitly scheduled days from `from` to `to` inclusive.
+    pub fn stats(&self, from: NaiveDate, to: NaiveDate, group_by: GroupBy) -> Stats {
+        let mut stats = Stats::default();
+        let mut groups: HashMap<&str, usize> = HashMap::new();
+
+        for (date, state) in self.range(from, to) {
+            let minutes = state.total_scheduled();
+            stats.total_minutes += minutes;
+            stats.task_count += state.tasks.len();
+
+            let busiest = stats.busiest_day.as_ref().map_or(0, |day| day.minutes);
+            if minutes > busiest {
+                stats.busiest_day = Some(DayTotal {
+                    date: *date,
+                    minutes,
+                });
+            }
+
+            for task in &state.tasks {
+                let names: Vec<&str> = match group_by {
+                    GroupBy::Label => vec![&task.label],
+                    GroupBy::Tag if task.tags.is_empty() => vec!["untagged"],
+                    GroupBy::Tag => task
+                        .tags
+                        .iter()
+                        .map(|tag| tag.trim_start_matches('#'))
+                        .collect(),
+                };
+                for name in names {
+                    *groups.entry(name).or_default() += task.slot.duration;
+                }
+            }
+        }
+
+        stats.breakdown = groups
+            .into_iter()
+            .map(|(name, minutes)| GroupTotal {
+                name: name.to_owned(),
+                minutes,
+            })
+            .collect();
+        stats
+            .breakdown
+            .sort_by(|a, b| b.minutes.cmp(&a.minutes).then_with(|| a.name.cmp(&b.name)));
+        stats
+    }
+
+    /// [Sanitizes](sanitize_label) every label in the schedule, such as ones
+    /// edited by hand, returning how each one that changed was changed.
+    pub fn sanitize_labels(&mut self) -> Vec<String> {
+        let mut changes = vec![];
+        for (date, state) in &mut self.dates {
+            for task in &mut state.tasks {
+                if let Some(old) = task.sanitize_label() {
+                    changes.push(format!("{date}: {old:?} became {:?}", task.label));
+                }
+            }
+        }
+        for (name, tasks) in &mut self.templates {
+            for task in tasks {
+                if let Some(old) = task.sanitize_label() {
+                    changes.push(format!("template {name}: {old:?} became {:?}", task.label));
+                }
+            }
+        }
+        for recurring in &mut self.recurring {
+            let label = sanitize_label(&recurring.label);
+            if label != recurring.label {
+                let old = std::mem::replace(&mut recurring.label, label);
+                changes.push(format!("recurring: {old:?} became {:?}", recurring.label));
+            }
+        }
+        changes
+    }
+
+    /// The tasks of the template assigned to `date`'s weekday, if any.
+    pub fn template_for(&self, date: NaiveDate) -> Option<&[Task]> {
+        let name = self.weekday_templates.get(&date.weekday())?;
+        self.templates.get(name).map(Vec::as_slice)
+    }
+
+    /// All tasks on `date`: those explicitly created for it, or failing that
+    /// its weekday's template, plus any recurring tasks for its weekday that
+    /// don't overlap one of them.
+    pub fn tasks_for(&self, date: NaiveDate) -> Vec<Task> {
+        let mut tasks = match self.dates.get(&date) {
+            Some(state) => state.tasks.clone(),
+            None => self.template_for(date).unwrap_or_default().to_vec(),
+        };
+
+        let explicit = tasks.len();
+        for recurring in self.recurring.iter().filter(|r| r.occurs_on(date)) {
+            let clashes = tasks[..explicit]
+                .iter()
+                .any(|task| task.slot.overlaps(&recurring.slot));
+            if !clashes {
+                tasks.push(Task::new(recurring.slot, recurring.label.clone()));
+            }
+        }
+        tasks.sort();
+        tasks
+    }
+
+    /// The parts of the previous day's tasks that run past midnight into
+    /// `date`, each as a task starting at midnight.
+    pub fn carried_over(&self, date: NaiveDate) -> Vec<Task> {
+        let Some(previous) = date.pred_opt() else {
+            return vec![];
+        };
+        self.tasks_for(previous)
+            .into_iter()
+            .filter_map(|mut task| {
+                task.slot = task.slot.overflow()?;
+                Some(task)
+            })
+            .collect()
+    }
+
+    /// Like [`Schedule::tasks_for`], but including the tasks
+    /// [carried over](Schedule::carried_over) from the previous day.
+    pub fn tasks_with_carried(&self, date: NaiveDate) -> Vec<Task> {
+        let mut tasks = self.tasks_for(date);
+        tasks.extend(self.carried_over(date));
+        tasks.sort();
+        tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(start: &str, duration: usize, label: &str) -> Task {
+        let start = start.parse().unwrap();
+        Task::new(TimeSlot { start, duration }, label)
+    }
+
+    fn day(tasks: Vec<Task>) -> DayState {
+        DayState {
+            date: NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            tasks,
+        }
+    }
+
+    #[test]
+    fn resolve_conflicts_moves_tasks_that_start_together() {
+        let mut state = day(vec![
+            task("09:00", 30, "Standup"),
+            task("09:00", 30, "Dentist"),
+            task("09:00", 30, "Dentist"),
+        ]);
+        state.resolve_conflicts();
+        assert_eq!(
+            state.tasks,
+            [task("09:00", 30, "Dentist"), task("09:30", 30, "Standup")]
+        );
+        assert!(state.conflicts().is_empty());
+    }
+
+    #[test]
+    fn import_ical_skips_events_with_huge_durations() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Forever\nDTSTART:20240603T090000\n\
+            DURATION:P2000000000W\nEND:VEVENT\nEND:VCALENDAR\n";
+        let mut schedule = Schedule::default();
+        let report = schedule.import_ical(ics, 5).unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.skipped, ["\"Forever\" has no valid end"]);
+    }
+
+    #[test]
+    fn import_csv_skips_rows_longer_than_a_day() {
+        let csv = "2024-06-03,09:00,18446744073709551615,X\n2024-06-03,10:00,30,Y\n";
+        let mut schedule = Schedule::default();
+        let report = schedule.import_csv(csv, 5);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.skipped[0].contains("longer than a day"));
+    }
+
+    #[test]
+    fn diff_is_signed_and_doesnt_wrap() {
+        let [morning, afternoon] = [Time::new(9, 30), Time::new(14, 15)];
+        assert_eq!(afternoon.diff(morning), 285);
+        assert_eq!(morning.diff(afternoon), -285);
+        assert_eq!(morning.minutes_until(afternoon), 285);
+        assert_eq!(afternoon.minutes_until(morning), -285);
+        assert_eq!(morning.diff(morning), 0);
+        assert_eq!(morning.minutes_until(morning), 0);
+    }
+
+    #[test]
+    fn clamp_keeps_times_within_bounds() {
+        let [min, max] = [Time::new(7, 0), Time::new(18, 55)];
+        assert_eq!(Time::new(6, 0).clamp(min, max), min);
+        assert_eq!(Time::new(12, 0).clamp(min, max), Time::new(12, 0));
+        assert_eq!(Time::new(20, 0).clamp(min, max), max);
+    }
+
+    #[test]
+    #[should_panic(expected = "min 18:55 > max 07:00")]
+    fn clamp_panics_when_bounds_are_inverted() {
+        Time::new(12, 0).clamp(Time::new(18, 55), Time::new(7, 0));
+    }
+
+    #[test]
+    fn is_between_includes_start_and_excludes_end() {
+        let [start, end] = [Time::new(9, 0), Time::new(17, 0)];
+        assert!(start.is_between(start, end));
+        assert!(!end.is_between(start, end));
+        assert!(start.is_between_inclusive(start, end));
+        assert!(end.is_between_inclusive(start, end));
+        assert!(!Time::new(8, 59).is_between_inclusive(start, end));
+        assert!(!Time::new(17, 1).is_between_inclusive(start, end));
+    }
+
+    #[test]
+    fn contains_handles_slots_crossing_midnight() {
+        let late = TimeSlot {
+            start: Time::new(23, 0),
+            duration: 120,
+        };
+        assert!(late.spans_midnight());
+        assert_eq!(late.end(), Time::new(1, 0));
+        assert!(late.contains(Time::new(23, 30)));
+        // After midnight is the next day, covered by the overflow instead
+        assert!(!late.contains(Time::new(0, 30)));
+        assert!(!late.contains(Time::new(2, 0)));
+
+        let overflow = late.overflow().unwrap();
+        assert_eq!(overflow.start, Time::new(0, 0));
+        assert_eq!(overflow.duration, 60);
+        assert!(overflow.contains(Time::new(0, 30)));
+        assert!(!overflow.contains(Time::new(2, 0)));
+
+        let until_midnight = TimeSlot {
+            start: Time::new(23, 0),
+            duration: 60,
+        };
+        assert_eq!(until_midnight.overflow(), None);
+        assert_eq!(TimeSlot::until(Time::new(23, 0), Time::new(1, 0)), late);
+    }
+
+    #[test]
+    fn carried_over_continues_the_previous_days_tasks() {
+        let state = day(vec![
+            task("09:00", 30, "Standup"),
+            task("23:00", 120, "Late"),
+        ]);
+        let next_day = state.date.succ_opt().unwrap();
+        let mut schedule = Schedule::default();
+        schedule.dates.insert(state.date, state);
+
+        assert_eq!(schedule.carried_over(next_day), [task("00:00", 60, "Late")]);
+        assert_eq!(
+            schedule.tasks_with_carried(next_day),
+            [task("00:00", 60, "Late")]
+        );
+        assert!(schedule.tasks_for(next_day).is_empty());
+    }
+
+    #[test]
+    fn split_at_cuts_strictly_inside_the_slot() {
+        let slot = TimeSlot {
+            start: Time::new(9, 0),
+            duration: 60,
+        };
+        let (first, second) = slot.split_at(Time::new(9, 20)).unwrap();
+        assert_eq!((first.start, first.duration), (Time::new(9, 0), 20));
+        assert_eq!((second.start, second.duration), (Time::new(9, 20), 40));
+        assert_eq!(first.duration + second.duration, slot.duration);
+
+        assert_eq!(slot.split_at(Time::new(9, 0)), None);
+        assert_eq!(slot.split_at(Time::new(10, 0)), None);
+        assert_eq!(slot.split_at(Time::new(11, 0)), None);
+    }
+
+    #[test]
+    fn gaps_skip_back_to_back_tasks_and_stop_at_the_window() {
+        let state = day(vec![
+            task("08:30", 60, "Early"),
+            task("09:30", 30, "Standup"),
+            task("11:00", 120, "Long"),
+        ]);
+        let within = TimeSlot {
+            start: Time::new(9, 0),
+            duration: 180,
+        };
+        let gap = TimeSlot {
+            start: Time::new(10, 0),
+            duration: 60,
+        };
+        assert_eq!(state.gaps(within), [gap]);
+        assert_eq!(day(vec![]).gaps(within), [within]);
+    }
+
+    #[test]
+    fn conflicts_finds_every_pair_in_a_three_way_overlap() {
+        let state = day(vec![
+            task("09:00", 60, "A"),
+            task("09:15", 30, "B"),
+            task("09:30", 60, "C"),
+            task("11:00", 30, "D"),
+        ]);
+        assert!(state.has_conflicts());
+        assert_eq!(state.conflicts(), [(0, 1), (0, 2), (1, 2)]);
+        assert!(!day(vec![task("09:00", 30, "A"), task("09:30", 30, "B")]).has_conflicts());
+    }
+
+    /// A schedule with an empty day on each of the given days of June 2024.
+    fn june(days: &[u32]) -> Schedule {
+        let mut schedule = Schedule::default();
+        for &d in days {
+            let date = NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+            schedule.dates.insert(
+                date,
+                DayState {
+                    date,
+                    tasks: vec![],
+                },
+            );
+        }
+        schedule
+    }
+
+    #[test]
+    fn range_returns_scheduled_days_in_order() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+        let schedule = june(&[10, 3, 7, 1]);
+        let dates = |from, to| -> Vec<NaiveDate> {
+            schedule.range(from, to).map(|(date, _)| *date).collect()
+        };
+
+        assert_eq!(dates(date(2), date(8)), [date(3), date(7)]);
+        assert_eq!(
+            dates(date(1), date(10)),
+            [date(1), date(3), date(7), date(10)]
+        );
+        assert!(dates(date(4), date(6)).is_empty());
+        assert!(dates(date(8), date(2)).is_empty());
+    }
+
+    #[test]
+    fn labels_keep_their_colors() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+
+        // Changing any of these recolors everyone's schedule
+        let palette = Palette {
+            colors: &[[0; 3]; 12],
+            stable: false,
+        };
+        let indices =
+            ["Standup", "Lunch", "Deep work", "Gym"].map(|label| color_index(label, palette));
+        assert_eq!(indices, [10, 11, 4, 0]);
+    }
+
+    #[test]
+    fn prune_before_keeps_the_day_itself() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        let mut schedule = june(&[1, 6, 7, 8]);
+        assert_eq!(schedule.prune_before(today), 2);
+        let dates: Vec<NaiveDate> = schedule.dates.keys().copied().collect();
+        assert_eq!(dates, [today, today.succ_opt().unwrap()]);
+        assert_eq!(schedule.prune_before(today), 0);
+    }
+
+    #[test]
+    fn render_tape_restarts_labels_of_separate_tasks() {
+        let palette = Palette {
+            colors: &[[1, 2, 3]],
+            stable: false,
+        };
+        let from = "09:00".parse().unwrap();
+
+        let gap = day(vec![task("09:00", 20, "Read"), task("09:30", 20, "Read")]);
+        let tape = render_tape(&gap, from, 12, 5, None, palette, Format::Text);
+        assert_eq!(tape, "Read  Read  ");
+
+        let back_to_back = day(vec![task("09:00", 15, "Read"), task("09:15", 15, "Read")]);
+        let tape = render_tape(&back_to_back, from, 6, 5, None, palette, Format::Text);
+        assert_eq!(tape, "ReaRea");
+    }
+
+    #[test]
+    fn render_tape_styles_tmux_runs() {
+        let palette = Palette {
+            colors: &[[1, 2, 3]],
+            stable: false,
+        };
+        let state = day(vec![task("09:00", 10, "Gym")]);
+        let from = "08:50".parse().unwrap();
+
+        let tape = render_tape(&state, from, 6, 5, None, palette, Format::Tmux);
+        assert_eq!(tape, "  #[bg=#010203]Gy#[bg=default]  #[bg=default]");
+
+        let now = Some("09:07".parse().unwrap());
+        let tape = render_tape(&state, from, 4, 5, now, palette, Format::Tmux);
+        assert_eq!(tape, "  #[bg=#010203]G|#[bg=default]");
+
+        let tape = render_tape(&state, from, 6, 5, now, palette, Format::Text);
+        assert_eq!(tape, "  G|  ");
+    }
+
+    #[test]
+    fn render_tape_samples_the_middle_of_wide_steps() {
+        let palette = Palette {
+            colors: &[[1, 2, 3]],
+            stable: false,
+        };
+        let state = day(vec![task("09:00", 60, "Lunch"), task("10:00", 10, "Call")]);
+        let from = "08:00".parse().unwrap();
+
+        // The call ends before the middle of its half hour, so doesn't show
+        let tape = render_tape(&state, from, 6, 30, None, palette, Format::Text);
+        assert_eq!(tape, "  Lu  ");
+
+        let now = Some("09:40".parse().unwrap());
+        let tape = render_tape(&state, from, 6, 30, now, palette, Format::Text);
+        assert_eq!(tape, "  L|  ");
+    }
+
+    #[test]
+    fn conflicts_with_includes_the_carried_over_tasks() {
+        let monday = day(vec![task("23:00", 120, "Late")]);
+        let mut schedule = Schedule::default();
+        schedule.dates.insert(monday.date, monday);
+        let tuesday = NaiveDate::from_ymd_opt(2024, 6, 4).unwrap();
+        let state = DayState {
+            date: tuesday,
+            tasks: vec![task("00:15", 30, "Early"), task("01:00", 30, "After")],
+        };
+
+        let carried = schedule.carried_over(tuesday);
+        let conflicts = state.conflicts_with(&carried);
+        let labels: Vec<_> = conflicts
+            .iter()
+            .map(|(a, b)| [&a.label, &b.label])
+            .collect();
+        assert_eq!(labels, [["Late", "Early"]]);
+        assert!(state.conflicts().is_empty());
+    }
+
+    #[test]
+    fn render_tape_stops_at_midnight() {
+        let palette = Palette {
+            colors: &[[1, 2, 3]],
+            stable: false,
+        };
+        let state = day(vec![task("00:00", 60, "Early"), task("23:40", 10, "Late")]);
+        let from = "23:30".parse().unwrap();
+
+        let tape = render_tape(&state, from, 12, 5, None, palette, Format::Text);
+        assert_eq!(tape, "  La        ");
+
+        let tape = render_tape(&state, from, 6, 5, None, palette, Format::Tmux);
+        assert_eq!(tape, "  #[bg=#010203]La#[bg=default]  #[bg=default]");
+    }
+
+    #[test]
+    fn twelve_hour_times_report_the_twelve_hour_range() {
+        let error = "14:30pm".parse::<Time>().unwrap_err();
+        assert_eq!(error, ParseTimeError::Hour12OutOfRange(14));
+        assert_eq!(
+            error.to_string(),
+            "hour 14 is out of range (expected 1-12 before am/pm)"
+        );
+        assert_eq!(
+            "0am".parse::<Time>(),
+            Err(ParseTimeError::Hour12OutOfRange(0))
+        );
+        assert_eq!(
+            "24:00".parse::<Time>(),
+            Err(ParseTimeError::HourOutOfRange(24))
+        );
+    }
 }
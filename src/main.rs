@@ -1,15 +1,23 @@
-use chrono::Timelike;
+mod config;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+use chrono::{Datelike, NaiveDate, Timelike};
 use clap::Parser;
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{bail, Result, WrapErr};
 use directories::ProjectDirs;
 use termbuffer::Color;
 
-use daytape::{DayState, Schedule, Task, Time, TimeSlot};
+use daytape::{task_color, DayState, GroupBy, Palette, Schedule, Task, Time, TimeSlot, DAY_MINS};
 
-const FIRST_HOUR: usize = 7;
-const LAST_HOUR: usize = 23;
-const DAY_START: Time = Time::new(FIRST_HOUR, 0);
-const DAY_END: Time = Time::new(LAST_HOUR, 55);
+use crate::config::{Config, Keys};
+
+const GRID_STEP: usize = 5;
+const DAY_START: Time = Time::new(7, 0);
+const DAY_END: Time = Time::new(23, 60 - GRID_STEP);
 
 /// A whole day's schedule in your terminal
 #[derive(Debug, Parser)]
@@ -18,6 +26,20 @@ struct Args {
     #[command(subcommand)]
     sub: Option<SubCommand>,
 
+    /// The schedule file to use instead of the default (also read from
+    /// DAYTAPE_FILE)
+    #[arg(long, global = true)]
+    file: Option<PathBuf>,
+
+    /// Use the named schedule in the config directory's `profiles` folder
+    /// instead of the default
+    #[arg(long, global = true, conflicts_with = "file", value_parser = parse_profile)]
+    profile: Option<String>,
+
+    /// Don't back up the schedule file before changing it
+    #[arg(long, global = true)]
+    no_backup: bool,
+
     #[command(flatten)]
     show_args: ShowArgs,
 }
@@ -35,7 +57,236 @@ enum SubCommand {
         /// Edit tomorrow's schedule instead of today's
         #[arg(long)]
         tomorrow: bool,
+
+        /// Edit the schedule for this date instead of today's
+        #[arg(long, conflicts_with = "tomorrow")]
+        date: Option<NaiveDate>,
+    },
+
+    /// Export the schedule to another format
+    Export {
+        /// The format to export to
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// The first date to export (defaults to today)
+        #[arg(long, alias = "date")]
+        from: Option<NaiveDate>,
+
+        /// The last date to export (defaults to the first)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import tasks from an iCalendar (.ics) or CSV file
+    Import {
+        /// The format of the file (defaults to csv for .csv files, and ical
+        /// otherwise)
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+
+        /// The file to import
+        path: PathBuf,
+
+        /// Print how the schedule would change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Add a task without opening the editor
+    Add {
+        /// The task's label
+        label: String,
+
+        /// When the task starts, like 14:00 or 2pm
+        #[arg(long, required_unless_present = "after", conflicts_with = "after")]
+        start: Option<Time>,
+
+        /// Start the task this long from now, like 30m, 1h or 1h30m
+        #[arg(long = "in", value_name = "DURATION", value_parser = parse_minutes)]
+        #[arg(conflicts_with = "date")]
+        after: Option<usize>,
+
+        /// How long the task lasts, in minutes or like 1h30m (defaults to
+        /// default_duration from the config file)
+        #[arg(long, value_parser = parse_minutes)]
+        duration: Option<usize>,
+
+        /// The date to add the task to (defaults to today)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Add the task even if it overlaps another
+        #[arg(long)]
+        force: bool,
+
+        /// Print how the schedule would change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove tasks without opening the editor
+    Remove {
+        /// Remove the task happening at this time
+        #[arg(long, required_unless_present = "label", conflicts_with = "label")]
+        at: Option<Time>,
+
+        /// Remove the tasks with exactly this label
+        #[arg(long)]
+        label: Option<String>,
+
+        /// The date to remove tasks from (defaults to today)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Print how the schedule would change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Copy one day's tasks to another date
+    Copy {
+        /// The date to copy tasks from
+        #[arg(long)]
+        from: NaiveDate,
+
+        /// The date to copy tasks to
+        #[arg(long)]
+        to: NaiveDate,
+
+        /// Replace any tasks already on the target date
+        #[arg(long)]
+        force: bool,
+
+        /// Print how the schedule would change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Remove every task on a date
+    Clear {
+        /// The date to clear (defaults to today)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+
+        /// Print how the schedule would change without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Keep running, and show a desktop notification as each task starts
+    Watch {
+        /// Notify this many minutes before each task starts
+        #[arg(long, default_value_t = 0)]
+        lead: usize,
     },
+
+    /// Add a template's tasks to a day, skipping any that overlap its tasks
+    ApplyTemplate {
+        /// The name of the template
+        name: String,
+
+        /// The date to add the tasks to (defaults to today)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+
+    /// Print the day's tasks, one per line
+    List {
+        /// List tomorrow's tasks instead of today's
+        #[arg(long)]
+        tomorrow: bool,
+
+        /// List the tasks for this date instead of today's
+        #[arg(long, conflicts_with = "tomorrow")]
+        date: Option<NaiveDate>,
+
+        /// Print the tasks as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the task happening now and the one coming up next
+    Next,
+
+    /// Print the minutes left in the task happening now, or nothing if
+    /// there isn't one
+    Remaining,
+
+    /// Summarize the time scheduled over a range of days
+    Stats {
+        /// The first date to include (defaults to six days before the last)
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// The last date to include (defaults to today)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+
+        /// Break the time down by tag instead of by label
+        #[arg(long)]
+        by_tag: bool,
+
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List overlapping tasks, failing if there are any
+    Check {
+        /// The first date to check (defaults to the earliest)
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// The last date to check (defaults to the latest)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+
+        /// Shorten tasks so that none of them overlap
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// List the schedules that can be picked with --profile
+    Profiles,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    /// iCalendar (.ics)
+    Ical,
+    /// Comma-separated values
+    Csv,
+    /// An SVG image with a timeline for each day
+    Svg,
+    /// A Markdown task list for each day
+    #[value(name = "md", alias = "markdown")]
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ImportFormat {
+    /// iCalendar (.ics)
+    Ical,
+    /// Comma-separated `date,start,duration,label` rows
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ShowFormat {
+    /// A colored tape for the tmux status line
+    Tmux,
+    /// The day's tasks and the current task as JSON
+    Json,
+    /// The tape with terminal colors, or no colors when not a terminal
+    Plain,
+    /// The current task as a waybar custom module, with the day's tasks in
+    /// the tooltip
+    Waybar,
 }
 
 #[derive(Parser, Debug)]
@@ -44,9 +295,45 @@ struct ShowArgs {
     #[arg(long)]
     tomorrow: bool,
 
+    /// Show the schedule for this date instead of today's
+    #[arg(long, conflicts_with = "tomorrow")]
+    date: Option<NaiveDate>,
+
+    /// The output format (defaults to tmux inside tmux, and plain elsewhere)
+    #[arg(long, value_enum)]
+    format: Option<ShowFormat>,
+
+    /// Show the whole week, one day per line, squeezing each day into the
+    /// tape
+    #[arg(long)]
+    week: bool,
+
     /// The number of characters to display in the day tape
     #[arg(short, long, default_value_t = 48)]
     width: u32,
+
+    /// The minutes covered by each character of the tape
+    #[arg(long, default_value_t = 1, conflicts_with = "week")]
+    #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+    minutes_per_char: u32,
+
+    /// Start the tape at this time rather than now (defaults to tape_from
+    /// from the config file)
+    #[arg(long, conflicts_with = "week")]
+    from: Option<Time>,
+
+    /// Show how far through the current task you are after its label, on
+    /// the tmux or plain tape
+    #[arg(long, conflicts_with = "week")]
+    progress: bool,
+
+    /// Add each task's duration after its label, where the tape has room
+    #[arg(long)]
+    show_duration: bool,
+
+    /// List the labels under each color after the tape
+    #[arg(long, conflicts_with = "week")]
+    legend: bool,
 }
 
 const COLORS: &[[u8; 3]] = &[
@@ -64,130 +351,1656 @@ const COLORS: &[[u8; 3]] = &[
     [190, 90, 0],
 ];
 
-fn get_color_index(label: &str) -> usize {
-    let digest = md5::compute(label.as_bytes());
-    let bytes: [u8; 16] = digest.into();
-    let number: u128 = u128::from_le_bytes(bytes);
-    (number % COLORS.len() as u128) as usize
+/// Gives every task without a color of its own one that no other label in
+/// `tasks` is using, preferring its hashed color. Once the palette runs out,
+/// the remaining labels fall back to their hashed colors.
+fn assign_distinct_colors(tasks: &mut [Task], palette: Palette) {
+    let mut used: Vec<[u8; 3]> = tasks.iter().filter_map(|task| task.color).collect();
+    let mut assigned: HashMap<String, [u8; 3]> = HashMap::new();
+
+    for task in tasks.iter_mut().filter(|task| task.color.is_none()) {
+        let key = task.color_key().to_owned();
+        let color = *assigned.entry(key).or_insert_with_key(|key| {
+            let preferred = daytape::color_index(key, palette);
+            let color = (0..palette.len())
+                .map(|i| palette[(preferred + i) % palette.len()])
+                .find(|color| !used.contains(color))
+                .unwrap_or(palette[preferred]);
+            used.push(color);
+            color
+        });
+        task.color = Some(color);
+    }
+}
+
+/// The keys that move the cursor in normal mode, with `vim_keys`. `i` also
+/// switches to insert mode there.
+const VIM_KEYS: [(char, termbuffer::Key); 4] = [
+    ('h', termbuffer::Key::Left),
+    ('j', termbuffer::Key::Down),
+    ('k', termbuffer::Key::Up),
+    ('l', termbuffer::Key::Right),
+];
+
+/// The keys that act in normal mode, with `vim_keys`, and are otherwise typed
+/// into labels. None of them can be rebound.
+const NORMAL_KEYS: &[(char, &str)] = &[
+    ('<', "move the task earlier (with vim_keys)"),
+    ('>', "move the task later (with vim_keys)"),
+    ('/', "search labels (with vim_keys)"),
+    ('?', "show this help (with vim_keys)"),
+];
+
+/// The editor's keys with their current bindings, listed in its help
+/// overlay.
+fn key_help(keys: &Keys) -> Vec<(String, &'static str)> {
+    let scale = format!("{} {}", keys.scale_down, keys.scale_up);
+    let vim = VIM_KEYS.map(|(key, _)| key.to_string()).join(" ");
+    let mut help = vec![
+        ("arrows".to_owned(), "move the cursor"),
+        (vim, "move the cursor (with vim_keys)"),
+        ("i / Esc".to_owned(), "insert / normal mode (with vim_keys)"),
+        ("typing".to_owned(), "create a task or add to its label"),
+        (
+            "Backspace".to_owned(),
+            "remove the last character of the label",
+        ),
+        (scale, "shorten / lengthen the task"),
+        (keys.extend.to_string(), "lengthen the task to the next one"),
+        ("Alt-< Alt->".to_owned(), "move the task earlier / later"),
+        (
+            keys.command.to_string(),
+            "type a command, then Enter to run it",
+        ),
+        ("Ctrl-R".to_owned(), "redo"),
+    ];
+    help.extend(
+        NORMAL_KEYS
+            .iter()
+            .map(|&(key, effect)| (key.to_string(), effect)),
+    );
+    help
+}
+
+/// The editor's commands with their current bindings, listed in its help
+/// overlay and under the command line.
+fn command_help(keys: &Keys) -> Vec<(String, &'static str)> {
+    let rebindable = [
+        (keys.save, "save"),
+        (keys.quit, "save & quit"),
+        (keys.delete, "delete"),
+    ];
+    rebindable
+        .into_iter()
+        .map(|(key, effect)| (key.to_string(), effect))
+        .chain(
+            COMMAND_HELP
+                .iter()
+                .map(|&(name, effect)| (name.to_owned(), effect)),
+        )
+        .collect()
+}
+
+/// The editor's commands that can't be rebound, with their arguments.
+const COMMAND_HELP: &[(&str, &str)] = &[
+    ("x", "quit"),
+    ("c", "complete"),
+    ("p", "paint"),
+    ("n", "note"),
+    ("l", "load template"),
+    ("dup", "duplicate the task"),
+    ("u", "undo"),
+    ("r", "redo"),
+    ("t START END", "set times"),
+    ("dur MINUTES", "set duration"),
+    ("copy DATE", "copy the day"),
+    ("clear", "clear the day"),
+    ("/ TEXT", "search labels"),
+    ("?", "show this help"),
+];
+
+/// Parses the `START END` arguments of the editor's `:t` command into a slot
+/// on the grid.
+fn parse_slot(args: &[&str], grid_step: usize) -> std::result::Result<TimeSlot, String> {
+    let [start, end] = args else {
+        return Err("usage: :t START END".to_owned());
+    };
+    let parse = |time: &str| {
+        time.parse::<Time>()
+            .map(|time| time.round_to_nearest(grid_step))
+            .map_err(|e| e.to_string())
+    };
+    let [start, end] = [parse(start)?, parse(end)?];
+    if end <= start {
+        return Err(format!("{end} is not after {start}"));
+    }
+    Ok(TimeSlot {
+        start,
+        duration: start.minutes_until(end) as usize,
+    })
+}
+
+/// Parses the `MINUTES` argument of the editor's `:dur` command.
+fn parse_duration(args: &[&str], grid_step: usize) -> std::result::Result<usize, String> {
+    let [minutes] = args else {
+        return Err("usage: :dur MINUTES".to_owned());
+    };
+    match minutes.parse::<usize>() {
+        Ok(minutes) if minutes > 0 && minutes.is_multiple_of(grid_step) => Ok(minutes),
+        _ => Err(format!(
+            "{minutes} is not a positive multiple of {grid_step} minutes"
+        )),
+    }
+}
+
+/// Steps the task's color override through the palette, then back to the
+/// hashed color.
+fn cycle_task_color(task: &mut Task, palette: Palette) {
+    let position = task
+        .color
+        .and_then(|color| palette.iter().position(|&c| c == color));
+    task.color = match position {
+        Some(i) => palette.get(i + 1).copied(),
+        None => Some(palette[0]),
+    };
+}
+
+fn get_edit_color([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb(r, g, b)
+}
+
+fn get_tmux_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Whether to emit colors at all, following the `NO_COLOR` convention.
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+fn get_dirs() -> ProjectDirs {
+    let dirs = ProjectDirs::from("com", "mistodon", "daytape").unwrap();
+    std::fs::create_dir_all(dirs.cache_dir()).unwrap();
+    std::fs::create_dir_all(dirs.config_dir()).unwrap();
+    dirs
+}
+
+/// The schedule file: `file` if given, then the `profile`'s file, then
+/// `$DAYTAPE_FILE`, then `daytape.yaml` in the config directory.
+fn get_main_file(file: Option<PathBuf>, profile: Option<&str>) -> PathBuf {
+    if let Some(file) = file {
+        return file;
+    }
+    if let Some(profile) = profile {
+        let mut profile_file = get_profiles_dir();
+        profile_file.push(format!("{profile}.yaml"));
+        return profile_file;
+    }
+    if let Some(file) = std::env::var_os("DAYTAPE_FILE") {
+        return PathBuf::from(file);
+    }
+
+    let dirs = get_dirs();
+    let mut main_file = dirs.config_dir().to_owned();
+    main_file.push("daytape.yaml");
+    main_file
+}
+
+fn get_profiles_dir() -> PathBuf {
+    let dirs = get_dirs();
+    let mut profiles_dir = dirs.config_dir().to_owned();
+    profiles_dir.push("profiles");
+    std::fs::create_dir_all(&profiles_dir).unwrap();
+    profiles_dir
+}
+
+/// Checks that a profile name can be used as a file name, without reaching
+/// outside the profiles folder.
+fn parse_profile(name: &str) -> std::result::Result<String, String> {
+    let valid = |ch: char| ch.is_alphanumeric() || ch == '-' || ch == '_';
+    match !name.is_empty() && name.chars().all(valid) {
+        true => Ok(name.to_owned()),
+        false => Err(format!(
+            "{name:?} is not a profile name (use letters, digits, - and _)"
+        )),
+    }
+}
+
+fn get_config_file() -> PathBuf {
+    let dirs = get_dirs();
+    let mut config_file = dirs.config_dir().to_owned();
+    config_file.push("config.toml");
+    config_file
+}
+
+fn target_date(
+    config: &Config,
+    now: chrono::DateTime<chrono::Local>,
+    date: Option<NaiveDate>,
+    tomorrow: bool,
+) -> chrono::NaiveDate {
+    if let Some(date) = date {
+        return date;
+    }
+
+    let rollover = config.rollover && now.hour() as usize > config.rollover_hour;
+    let tomorrow = tomorrow || rollover;
+    let offset = match tomorrow {
+        true => 1,
+        _ => 0,
+    };
+    (now + chrono::Duration::days(offset)).date_naive()
+}
+
+/// The date for commands that change the schedule, which default to today
+/// whatever the time, unlike [`target_date`], so they never change tomorrow
+/// by surprise.
+fn date_or_today(date: Option<NaiveDate>) -> NaiveDate {
+    date.unwrap_or_else(|| chrono::Local::now().date_naive())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut config = Config::load(&get_config_file())?;
+    if args.no_backup {
+        config.backups = 0;
+    }
+    let main_file = get_main_file(args.file, args.profile.as_deref());
+
+    match args.sub {
+        Some(SubCommand::Edit { tomorrow, date }) => {
+            // Start a new profile with an empty file, so it's listed straight away
+            if args.profile.is_some() && !main_file.exists() {
+                std::fs::write(&main_file, "")
+                    .wrap_err_with(|| format!("failed to create {}", main_file.display()))?;
+            }
+            edit(&config, &main_file, date, tomorrow)
+        }
+        Some(SubCommand::Show { show_args }) => show(&config, &main_file, &show_args),
+        Some(SubCommand::Export {
+            format,
+            from,
+            to,
+            output,
+        }) => export(&config, &main_file, format, from, to, output),
+        Some(SubCommand::Import {
+            format,
+            path,
+            dry_run,
+        }) => import(&config, &main_file, format, &path, dry_run),
+        Some(SubCommand::Add {
+            label,
+            start,
+            after,
+            duration,
+            date,
+            force,
+            dry_run,
+        }) => {
+            let now = chrono::Local::now();
+            let (date, start) = match (start, after) {
+                (_, Some(after)) => {
+                    let at = now + chrono::Duration::minutes(after as i64);
+                    let time = Time::new(at.hour() as usize, at.minute() as usize);
+                    (Some(at.date_naive()), time)
+                }
+                (Some(start), None) => (date, start),
+                (None, None) => bail!("either --start or --in is needed"),
+            };
+            let slot = TimeSlot {
+                start,
+                duration: duration.unwrap_or(config.default_duration()),
+            };
+            add(&config, &main_file, label, slot, date, force, dry_run)
+        }
+        Some(SubCommand::Remove {
+            at,
+            label,
+            date,
+            dry_run,
+        }) => remove(&config, &main_file, at, label, date, dry_run),
+        Some(SubCommand::Copy {
+            from,
+            to,
+            force,
+            dry_run,
+        }) => copy(&config, &main_file, from, to, force, dry_run),
+        Some(SubCommand::Clear { date, dry_run }) => clear(&config, &main_file, date, dry_run),
+        Some(SubCommand::ApplyTemplate { name, date }) => {
+            apply_template(&config, &main_file, &name, date)
+        }
+        Some(SubCommand::Watch { lead }) => watch(&config, &main_file, lead),
+        Some(SubCommand::List {
+            tomorrow,
+            date,
+            json,
+        }) => list(&config, &main_file, date, tomorrow, json),
+        Some(SubCommand::Next) => next(&main_file),
+        Some(SubCommand::Remaining) => remaining(&main_file),
+        Some(SubCommand::Stats {
+            from,
+            to,
+            by_tag,
+            json,
+        }) => stats(&main_file, from, to, by_tag, json),
+        Some(SubCommand::Check { from, to, fix }) => check(&config, &main_file, from, to, fix),
+        Some(SubCommand::Profiles) => profiles(),
+        None => show(&config, &main_file, &args.show_args),
+    }
+}
+
+/// Loads the schedule, or an empty one if the file doesn't exist yet. A file
+/// that exists but can't be read or parsed is an error, so that it's never
+/// overwritten with an empty schedule.
+fn load_schedule(path: &std::path::Path) -> Result<Schedule> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Schedule::default()),
+        Err(e) => {
+            return Err(e).wrap_err_with(|| format!("failed to read {}", path.display()));
+        }
+    };
+    if source.trim().is_empty() {
+        return Ok(Schedule::default());
+    }
+
+    let mut schedule: Schedule = serde_yaml::from_str(&source)
+        .wrap_err_with(|| format!("invalid schedule file {}", path.display()))?;
+    for change in schedule.sanitize_labels() {
+        eprintln!("Removed control characters: {change}");
+    }
+    Ok(schedule)
+}
+
+/// Copies the schedule file into the cache directory before it's overwritten,
+/// keeping only the latest `keep` copies of it.
+fn backup_schedule(path: &std::path::Path, keep: usize) -> Result<()> {
+    if keep == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    // Each schedule file gets its own folder, named after its full path, so
+    // that files with similar or equal names never prune each other's backups
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "daytape".to_owned());
+    let full_path = std::fs::canonicalize(path)?;
+    let hash = daytape::fnv1a(full_path.as_os_str().as_encoded_bytes());
+    let mut backup_dir = get_dirs().cache_dir().to_owned();
+    backup_dir.push("backups");
+    backup_dir.push(format!("{stem}-{hash:016x}"));
+    std::fs::create_dir_all(&backup_dir)?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%dT%H%M%S%.3f");
+    std::fs::copy(path, backup_dir.join(format!("{timestamp}.yaml")))?;
+
+    // Timestamps sort in order, so the oldest backups come first
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(&backup_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        std::fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// How many days before today to remember the editor's cursor for.
+const CURSOR_DAYS: u64 = 7;
+
+fn cursors_file() -> PathBuf {
+    get_dirs().cache_dir().join("cursors.yaml")
+}
+
+/// Where the editor's cursor was left on each date, or nothing if that can't
+/// be read.
+fn load_cursors() -> BTreeMap<NaiveDate, Time> {
+    std::fs::read_to_string(cursors_file())
+        .ok()
+        .and_then(|source| serde_yaml::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+/// Remembers where the editor's cursor was left on `date`, forgetting dates
+/// more than [`CURSOR_DAYS`] before `today`.
+fn save_cursor(date: NaiveDate, cursor: Time, today: NaiveDate) -> Result<()> {
+    let mut cursors = load_cursors();
+    cursors.insert(date, cursor);
+    if let Some(oldest) = today.checked_sub_days(chrono::Days::new(CURSOR_DAYS)) {
+        cursors.retain(|&date, _| date >= oldest);
+    }
+    std::fs::write(cursors_file(), serde_yaml::to_string(&cursors)?)?;
+    Ok(())
+}
+
+/// Writes the schedule to a temporary file next to `path` and renames it into
+/// place, so a crash part way through never leaves a truncated schedule. The
+/// previous file is backed up first unless `backups` is 0.
+fn save_schedule(path: &std::path::Path, schedule: &Schedule, backups: usize) -> Result<()> {
+    use std::io::Write;
+
+    let output = serde_yaml::to_string(schedule)?;
+    backup_schedule(path, backups)?;
+
+    let mut temp_file = path.as_os_str().to_owned();
+    temp_file.push(".tmp");
+    let temp_file = PathBuf::from(temp_file);
+
+    let written = std::fs::File::create(&temp_file).and_then(|mut file| {
+        file.write_all(output.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_file);
+        return Err(e.into());
+    }
+
+    std::fs::rename(&temp_file, path)?;
+    Ok(())
+}
+
+/// Prints how saving `schedule` would change the schedule file, and any
+/// conflicts on the days it changes, instead of saving it.
+fn preview(main_file: &std::path::Path, schedule: &Schedule) -> Result<()> {
+    let saved: Schedule = load_schedule(main_file)?;
+    let dates: BTreeSet<NaiveDate> = saved
+        .dates
+        .keys()
+        .chain(schedule.dates.keys())
+        .copied()
+        .collect();
+
+    let [mut added, mut removed] = [0, 0];
+    for date in dates {
+        let [before, after] = [&saved, schedule].map(|schedule| {
+            schedule
+                .dates
+                .get(&date)
+                .map_or(&[][..], |state| &state.tasks[..])
+        });
+
+        // Tasks in both are paired off one for one, leaving the changes
+        let mut unmatched: Vec<&Task> = after.iter().collect();
+        let mut gone = vec![];
+        for task in before {
+            match unmatched.iter().position(|&other| other == task) {
+                Some(i) => {
+                    unmatched.remove(i);
+                }
+                None => gone.push(task),
+            }
+        }
+        if gone.is_empty() && unmatched.is_empty() {
+            continue;
+        }
+
+        for (sign, task) in gone
+            .iter()
+            .map(|task| ('-', task))
+            .chain(unmatched.iter().map(|task| ('+', task)))
+        {
+            let [start, end] = [task.slot.start, task.slot.end()];
+            println!("{sign} {date} {start}-{end} {}", task.label);
+        }
+        removed += gone.len();
+        added += unmatched.len();
+
+        if let Some(state) = schedule.dates.get(&date) {
+            let carried = schedule.carried_over(date);
+            for (a, b) in state.conflicts_with(&carried) {
+                println!("! {date} {} overlaps {}", a.label, b.label);
+            }
+        }
+    }
+
+    println!("Dry run: would add {added} tasks and remove {removed}, nothing was saved");
+    Ok(())
+}
+
+/// The day's tasks, or `None` if nothing at all is planned for it.
+fn day_state(config: &Config, schedule: &Schedule, date: NaiveDate) -> Option<DayState> {
+    let mut tasks = schedule.tasks_with_carried(date);
+    if config.distinct_colors {
+        assign_distinct_colors(&mut tasks, config.palette());
+    }
+    match tasks.is_empty() && !schedule.dates.contains_key(&date) {
+        true => None,
+        false => Some(DayState { date, tasks }),
+    }
+}
+
+fn show(config: &Config, main_file: &std::path::Path, show_args: &ShowArgs) -> Result<()> {
+    use std::io::IsTerminal;
+
+    let now = chrono::Local::now();
+    let target_date = target_date(config, now, show_args.date, show_args.tomorrow);
+
+    let schedule: Schedule = load_schedule(main_file)?;
+
+    let time = Time::new(now.hour() as usize, now.minute() as usize);
+    let width = show_args.width as usize;
+    let step = show_args.minutes_per_char as usize;
+    let color = use_color();
+    let palette = config.palette();
+    // An explicit --format wins, then whether we're running inside tmux
+    let format = show_args
+        .format
+        .unwrap_or(match std::env::var_os("TMUX").is_some() {
+            true => ShowFormat::Tmux,
+            false => ShowFormat::Plain,
+        });
+
+    if show_args.week {
+        let monday = target_date
+            - chrono::Duration::days(target_date.weekday().num_days_from_monday() as i64);
+        let days: Vec<(NaiveDate, Option<DayState>)> = (0..7)
+            .map(|i| monday + chrono::Duration::days(i))
+            .map(|date| (date, day_state(config, &schedule, date)))
+            .collect();
+
+        // Squeeze the whole working day into each tape
+        let span = config.day_start.minutes_until(config.day_end) as usize + config.grid_step;
+        let step = span.div_ceil(width.max(1));
+        let from = config.day_start;
+
+        if let ShowFormat::Waybar = format {
+            bail!("--week can't be shown with --format waybar");
+        }
+
+        if let ShowFormat::Json = format {
+            let days: Vec<JsonDay> = days
+                .iter()
+                .map(|(date, state)| {
+                    let current = (*date == now.date_naive()).then_some(time);
+                    json_day(*date, state.as_ref(), current, palette)
+                })
+                .collect();
+            println!("{}", serde_json::to_string(&days)?);
+            return Ok(());
+        }
+
+        for (date, state) in &days {
+            let mut state = state.clone();
+            if let Some(state) = state.as_mut().filter(|_| show_args.show_duration) {
+                add_durations(state, step, palette);
+            }
+            let tape = match format {
+                ShowFormat::Tmux => tmux(state.as_ref(), from, width, step, None, color, palette),
+                _ => {
+                    let color = color && std::io::stdout().is_terminal();
+                    plain(state.as_ref(), from, width, step, None, color, palette)
+                }
+            };
+            println!("{} {date} {tape}", date.weekday());
+        }
+        return Ok(());
+    }
+
+    let mut state = day_state(config, &schedule, target_date);
+    let is_today = target_date == now.date_naive();
+    // A fixed start keeps the tape still, so mark where now is on it instead
+    let fixed_from = show_args.from.or(config.tape_from);
+    let from = match (fixed_from, is_today) {
+        (Some(from), _) => from,
+        (None, true) => time.max(config.day_start),
+        (None, false) => config.day_start,
+    };
+    let marker = (fixed_from.is_some() && is_today).then_some(time);
+
+    // From the tasks on the tape, or the whole day for the waybar tooltip,
+    // before their labels are changed below
+    let legend = state.as_ref().filter(|_| show_args.legend).map(|state| {
+        let tasks: Vec<&Task> = match format {
+            ShowFormat::Waybar => state.tasks.iter().collect(),
+            _ => (0..width)
+                .filter_map(|i| state.task_at(from + Time::mins(i * step + step / 2)))
+                .collect(),
+        };
+        legend(tasks, palette)
+    });
+    let legend = legend.as_deref().unwrap_or(&[]);
+
+    // Only the tape's labels are decorated, leaving the ones scripts read as
+    // they are
+    let tape_format = matches!(format, ShowFormat::Tmux | ShowFormat::Plain);
+    if let Some(state) = state
+        .as_mut()
+        .filter(|_| show_args.progress && is_today && tape_format)
+    {
+        add_progress(state, time, palette);
+    }
+    if let Some(state) = state
+        .as_mut()
+        .filter(|_| show_args.show_duration && tape_format)
+    {
+        add_durations(state, step, palette);
+    }
+    match format {
+        ShowFormat::Tmux => {
+            let tape = tmux(state.as_ref(), from, width, step, marker, color, palette);
+            let legend = legend.iter().map(|(swatch, labels)| match color {
+                true => format!(
+                    "#[bg={}] #[bg=default] {}",
+                    get_tmux_color(*swatch),
+                    labels.join(", ")
+                ),
+                false => labels.join(", "),
+            });
+            print!(
+                "{}",
+                std::iter::once(tape)
+                    .chain(legend)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        }
+        ShowFormat::Json => {
+            let current = is_today.then_some(time);
+            let day = json_day(target_date, state.as_ref(), current, palette);
+            println!("{}", serde_json::to_string(&day)?);
+        }
+        ShowFormat::Waybar => {
+            let current = is_today.then_some(time);
+            let mut output = waybar(state.as_ref(), current);
+            for ([r, g, b], labels) in legend {
+                let labels = xml_escape(&labels.join(", "));
+                output.tooltip +=
+                    &format!("\n<span background=\"#{r:02x}{g:02x}{b:02x}\">    </span> {labels}");
+            }
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        ShowFormat::Plain => {
+            let color = color && std::io::stdout().is_terminal();
+            let tape = plain(state.as_ref(), from, width, step, marker, color, palette);
+            let legend = legend.iter().map(|([r, g, b], labels)| match color {
+                true => format!("\x1b[48;2;{r};{g};{b}m \x1b[0m {}", labels.join(", ")),
+                false => labels.join(", "),
+            });
+            println!(
+                "{}",
+                std::iter::once(tape)
+                    .chain(legend)
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends the percentage of the task under `time` that has passed to its
+/// label, pinning its color so that the new label doesn't change it.
+fn add_progress(state: &mut DayState, time: Time, palette: Palette) {
+    if let Some(task) = state.task_at_mut(time) {
+        let elapsed = task.slot.start.minutes_until(time) as usize;
+        let percent = elapsed * 100 / task.slot.duration.max(1);
+        task.color = Some(task_color(task, palette));
+        task.label = format!("{} {percent}%", task.label);
+    }
+}
+
+/// Appends each task's duration to its label where the whole label still fits
+/// in the task on a tape of `step` minutes per character, pinning its color
+/// as [`add_progress`] does.
+fn add_durations(state: &mut DayState, step: usize, palette: Palette) {
+    for task in &mut state.tasks {
+        let label = format!("{} {}", task.label, format_duration(task.slot.duration));
+        if label.chars().count() <= task.slot.duration / step.max(1) {
+            task.color = Some(task_color(task, palette));
+            task.label = label;
+        }
+    }
+}
+
+/// Groups the labels of `tasks` by the color they're drawn in, in order of
+/// first appearance.
+fn legend<'a>(
+    tasks: impl IntoIterator<Item = &'a Task>,
+    palette: Palette,
+) -> Vec<([u8; 3], Vec<String>)> {
+    let mut legend: Vec<([u8; 3], Vec<String>)> = vec![];
+    for task in tasks {
+        let color = task_color(task, palette);
+        match legend.iter_mut().find(|(swatch, _)| *swatch == color) {
+            Some((_, labels)) if labels.contains(&task.label) => (),
+            Some((_, labels)) => labels.push(task.label.clone()),
+            None => legend.push((color, vec![task.label.clone()])),
+        }
+    }
+    legend
+}
+
+fn tmux(
+    state: Option<&DayState>,
+    from: Time,
+    width: usize,
+    step: usize,
+    now: Option<Time>,
+    color: bool,
+    palette: Palette,
+) -> String {
+    let Some(state) = state else {
+        return match color {
+            true => format!(
+                "#[bg=red]{: <width$}#[bg=default]",
+                "No task set",
+                width = width
+            ),
+            false => format!("{: <width$}", "No task set", width = width),
+        };
+    };
+
+    let format = match color {
+        true => daytape::Format::Tmux,
+        false => daytape::Format::Text,
+    };
+    daytape::render_tape(state, from, width, step, now, palette, format)
+}
+
+fn plain(
+    state: Option<&DayState>,
+    from: Time,
+    width: usize,
+    step: usize,
+    now: Option<Time>,
+    color: bool,
+    palette: Palette,
+) -> String {
+    let Some(state) = state else {
+        return match color {
+            true => format!("\x1b[41m{: <width$}\x1b[0m", "No task set", width = width),
+            false => "No task set".to_owned(),
+        };
+    };
+
+    let format = match color {
+        true => daytape::Format::Ansi,
+        false => daytape::Format::Text,
+    };
+    daytape::render_tape(state, from, width, step, now, palette, format)
+}
+
+#[derive(serde::Serialize)]
+struct JsonTask<'a> {
+    start: Time,
+    end: Time,
+    label: &'a str,
+    color: String,
+}
+
+impl<'a> JsonTask<'a> {
+    fn new(task: &'a Task, palette: Palette) -> Self {
+        JsonTask {
+            start: task.slot.start,
+            end: task.slot.end(),
+            label: &task.label,
+            color: get_tmux_color(task_color(task, palette)),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonDay<'a> {
+    date: NaiveDate,
+    tasks: Vec<JsonTask<'a>>,
+    current: Option<JsonTask<'a>>,
+}
+
+/// The day as it's shown in JSON. `now` is only given when showing today,
+/// since there is no current task on any other day.
+fn json_day<'a>(
+    date: NaiveDate,
+    state: Option<&'a DayState>,
+    now: Option<Time>,
+    palette: Palette,
+) -> JsonDay<'a> {
+    let tasks = state.map(|state| state.tasks.as_slice()).unwrap_or(&[]);
+    let current = state
+        .zip(now)
+        .and_then(|(state, now)| state.task_at(now))
+        .map(|task| JsonTask::new(task, palette));
+    JsonDay {
+        date,
+        tasks: tasks
+            .iter()
+            .map(|task| JsonTask::new(task, palette))
+            .collect(),
+        current,
+    }
+}
+
+/// The JSON object read by waybar's custom modules.
+#[derive(serde::Serialize)]
+struct Waybar {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
+
+fn waybar(state: Option<&DayState>, now: Option<Time>) -> Waybar {
+    let tasks = state.map(|state| state.tasks.as_slice()).unwrap_or(&[]);
+    let current = state.zip(now).and_then(|(state, now)| state.task_at(now));
+    let tooltip = tasks
+        .iter()
+        .map(|task| format!("{}-{}  {}", task.slot.start, task.slot.end(), task.label))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Waybar {
+        text: current.map(|task| task.label.clone()).unwrap_or_default(),
+        tooltip,
+        class: match current {
+            Some(_) => "active",
+            None => "idle",
+        },
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonList<'a> {
+    date: NaiveDate,
+    tasks: Vec<JsonTask<'a>>,
+    total_minutes: usize,
+}
+
+fn list(
+    config: &Config,
+    main_file: &std::path::Path,
+    date: Option<NaiveDate>,
+    tomorrow: bool,
+    json: bool,
+) -> Result<()> {
+    let target_date = target_date(config, chrono::Local::now(), date, tomorrow);
+    let schedule: Schedule = load_schedule(main_file)?;
+    let mut state = DayState {
+        date: target_date,
+        tasks: schedule.tasks_with_carried(target_date),
+    };
+    if config.distinct_colors {
+        assign_distinct_colors(&mut state.tasks, config.palette());
+    }
+
+    if json {
+        let list = JsonList {
+            date: target_date,
+            tasks: state
+                .tasks
+                .iter()
+                .map(|task| JsonTask::new(task, config.palette()))
+                .collect(),
+            total_minutes: state.total_scheduled(),
+        };
+        println!("{}", serde_json::to_string(&list)?);
+        return Ok(());
+    }
+
+    for task in &state.tasks {
+        println!("{}-{}  {}", task.slot.start, task.slot.end(), task.label);
+    }
+    println!("Total: {} minutes", state.total_scheduled());
+    Ok(())
+}
+
+/// Formats a number of minutes like `45m`, `2h` or `1h30m`.
+fn format_duration(minutes: usize) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, mins) => format!("{mins}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, mins) => format!("{hours}h{mins}m"),
+    }
+}
+
+/// Parses a duration given as minutes, or with units like `30m`, `1h` or
+/// `1h30m`.
+fn parse_minutes(text: &str) -> std::result::Result<usize, String> {
+    let invalid = || format!("{text} is not a duration like 45, 30m, 1h or 1h30m");
+    if let Ok(minutes) = text.parse() {
+        return Ok(minutes);
+    }
+
+    let (hours, rest) = match text.split_once('h') {
+        Some((hours, rest)) => (hours.parse::<usize>().map_err(|_| invalid())?, rest),
+        None => (0, text),
+    };
+    let minutes = match rest {
+        "" if text.ends_with('h') => 0,
+        rest => rest
+            .strip_suffix('m')
+            .and_then(|minutes| minutes.parse::<usize>().ok())
+            .ok_or_else(invalid)?,
+    };
+    Ok(hours * 60 + minutes)
+}
+
+/// Prints the name of each schedule in the profiles folder.
+fn profiles() -> Result<()> {
+    let dir = get_profiles_dir();
+    let entries =
+        std::fs::read_dir(&dir).wrap_err_with(|| format!("failed to read {}", dir.display()))?;
+
+    let mut names = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "yaml") {
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort();
+
+    if names.is_empty() {
+        println!("No profiles yet (start one with `daytape --profile NAME edit`)");
+    }
+    for name in names {
+        println!("{name}");
+    }
+    Ok(())
+}
+
+fn next(main_file: &std::path::Path) -> Result<()> {
+    let now = chrono::Local::now();
+    let time = Time::new(now.hour() as usize, now.minute() as usize);
+
+    let schedule: Schedule = load_schedule(main_file)?;
+    let state = DayState {
+        date: now.date_naive(),
+        tasks: schedule.tasks_with_carried(now.date_naive()),
+    };
+
+    let current = state.task_at(time);
+    let next = state.tasks.iter().find(|task| task.slot.start > time);
+
+    if current.is_none() && next.is_none() {
+        println!("Nothing scheduled");
+        return Ok(());
+    }
+
+    if let Some(task) = current {
+        let left = task.slot.minutes_left(time);
+        println!("Now: {} (ends in {})", task.label, format_duration(left));
+    }
+    if let Some(task) = next {
+        let until = time.minutes_until(task.slot.start) as usize;
+        println!("Next: {} (in {})", task.label, format_duration(until));
+    }
+    Ok(())
+}
+
+fn remaining(main_file: &std::path::Path) -> Result<()> {
+    let now = chrono::Local::now();
+    let time = Time::new(now.hour() as usize, now.minute() as usize);
+
+    let schedule: Schedule = load_schedule(main_file)?;
+    let state = DayState {
+        date: now.date_naive(),
+        tasks: schedule.tasks_with_carried(now.date_naive()),
+    };
+
+    // Print nothing at all when idle, so a status bar just shows a gap
+    if let Some(task) = state.task_at(time) {
+        println!("{}", task.slot.minutes_left(time));
+    }
+    Ok(())
+}
+
+fn watch(config: &Config, main_file: &std::path::Path, lead: usize) -> Result<()> {
+    let modified_at = |path: &std::path::Path| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+
+    let mut schedule: Schedule = load_schedule(main_file)?;
+    let mut modified = modified_at(main_file);
+    let mut checked_up_to = chrono::Local::now().naive_local();
+
+    loop {
+        let wait = 60 - chrono::Local::now().second().min(59);
+        std::thread::sleep(std::time::Duration::from_secs(wait as u64));
+
+        // Keep using the last good schedule if the file is mid-edit
+        let current = modified_at(main_file);
+        if current != modified {
+            modified = current;
+            match load_schedule(main_file) {
+                Ok(reloaded) => schedule = reloaded,
+                Err(e) => eprintln!("{e:#}"),
+            }
+        }
+
+        let now = chrono::Local::now().naive_local();
+        let passed = |at| checked_up_to < at && at <= now;
+        let today = now.date();
+        // Yesterday's tasks may end after midnight, and tomorrow's may be
+        // notified before it
+        let dates = [today.pred_opt().unwrap(), today, today.succ_opt().unwrap()];
+        for date in dates {
+            for task in schedule.tasks_for(date) {
+                let start = task.slot.start;
+                let starts_at = date
+                    .and_hms_opt(start.hour as u32, start.min as u32, 0)
+                    .unwrap();
+                let ends_at = starts_at + chrono::Duration::minutes(task.slot.duration as i64);
+                if passed(starts_at - chrono::Duration::minutes(lead as i64)) {
+                    if let Err(e) = notify(&task, starts_at - now) {
+                        eprintln!("{e:#}");
+                    }
+                }
+                if let Some(hook) = config.on_start.as_deref().filter(|_| passed(starts_at)) {
+                    run_hook(hook, &task);
+                }
+                if let Some(hook) = config.on_end.as_deref().filter(|_| passed(ends_at)) {
+                    run_hook(hook, &task);
+                }
+            }
+        }
+        checked_up_to = now;
+    }
+}
+
+/// Runs a hook command through the shell without waiting for it, passing
+/// the task in environment variables.
+fn run_hook(command: &str, task: &Task) {
+    let child = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("DAYTAPE_LABEL", &task.label)
+        .env("DAYTAPE_START", task.slot.start.to_string())
+        .env("DAYTAPE_END", task.slot.end().to_string())
+        .env("DAYTAPE_TAGS", task.tags.join(","))
+        .spawn();
+    match child {
+        // Reap the hook once it exits, without holding up the watcher
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("failed to run {command:?}: {e}"),
+    }
+}
+
+fn notify(task: &Task, until: chrono::Duration) -> Result<()> {
+    let minutes = until.num_minutes().max(0) as usize;
+    let body = match minutes {
+        0 => format!("Starting now ({})", task.slot.start),
+        _ => format!(
+            "Starts in {} ({})",
+            format_duration(minutes),
+            task.slot.start
+        ),
+    };
+    notify_rust::Notification::new()
+        .summary(&task.label)
+        .body(&body)
+        .appname("daytape")
+        .show()
+        .wrap_err("failed to show a notification")?;
+    Ok(())
+}
+
+fn stats(
+    main_file: &std::path::Path,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    by_tag: bool,
+    json: bool,
+) -> Result<()> {
+    let to = to.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let from = from.unwrap_or(to - chrono::Duration::days(6));
+    let group_by = match by_tag {
+        true => GroupBy::Tag,
+        false => GroupBy::Label,
+    };
+
+    let schedule: Schedule = load_schedule(main_file)?;
+    let stats = schedule.stats(from, to, group_by);
+
+    if json {
+        println!("{}", serde_json::to_string(&stats)?);
+        return Ok(());
+    }
+
+    println!("{from} to {to}");
+    println!(
+        "Total: {} minutes in {} tasks",
+        stats.total_minutes, stats.task_count
+    );
+    if let Some(day) = &stats.busiest_day {
+        println!(
+            "Busiest day: {} {} ({} minutes)",
+            day.date.weekday(),
+            day.date,
+            day.minutes
+        );
+    }
+
+    if !stats.breakdown.is_empty() {
+        let heading = match group_by {
+            GroupBy::Label => "Label",
+            GroupBy::Tag => "Tag",
+        };
+        let width = stats
+            .breakdown
+            .iter()
+            .map(|group| group.name.chars().count())
+            .chain([heading.len()])
+            .max()
+            .unwrap_or_default();
+
+        println!();
+        println!("{heading: <width$}  Minutes");
+        for group in &stats.breakdown {
+            println!("{: <width$}  {: >7}", group.name, group.minutes);
+        }
+    }
+    Ok(())
+}
+
+fn check(
+    config: &Config,
+    main_file: &std::path::Path,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    fix: bool,
+) -> Result<()> {
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let in_range =
+        |date: &NaiveDate| from.is_none_or(|from| *date >= from) && to.is_none_or(|to| *date <= to);
+    let mut found = 0;
+    for (date, state) in schedule.dates.iter().filter(|(date, _)| in_range(date)) {
+        let carried = schedule.carried_over(*date);
+        for (a, b) in state.conflicts_with(&carried) {
+            println!(
+                "{date}: {:?} ({}\u{2013}{}) overlaps {:?} ({}\u{2013}{})",
+                a.label,
+                a.slot.start,
+                a.slot.end(),
+                b.label,
+                b.slot.start,
+                b.slot.end()
+            );
+            found += 1;
+        }
+    }
+
+    if found == 0 {
+        println!("No conflicts");
+        return Ok(());
+    }
+    if !fix {
+        bail!("Found {found} conflicts");
+    }
+
+    for (_, state) in schedule.dates.iter_mut().filter(|(date, _)| in_range(date)) {
+        state.resolve_conflicts();
+    }
+    // Tasks carried over from the day before can't be moved from here, so
+    // any overlapping them are left too
+    let left: usize = schedule
+        .dates
+        .iter()
+        .filter(|(date, _)| in_range(date))
+        .map(|(date, state)| state.conflicts_with(&schedule.carried_over(*date)).len())
+        .sum();
+    save_schedule(main_file, &schedule, config.backups)?;
+    println!("Fixed {} conflicts", found - left);
+    if left > 0 {
+        bail!("{left} conflicts are with tasks running until or past midnight, and were left as they are");
+    }
+    Ok(())
+}
+
+fn export(
+    config: &Config,
+    main_file: &std::path::Path,
+    format: ExportFormat,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let from = from.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let to = to.unwrap_or(from);
+
+    let schedule: Schedule = load_schedule(main_file)?;
+    let exported = match format {
+        ExportFormat::Ical => schedule.to_ical(from, to),
+        ExportFormat::Csv => schedule.to_csv(from, to),
+        ExportFormat::Svg => svg(config, &schedule, from, to),
+        ExportFormat::Markdown => schedule.to_markdown(from, to),
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, exported)?,
+        None => print!("{exported}"),
+    }
+    Ok(())
 }
 
-fn get_edit_color(index: usize) -> Color {
-    let [r, g, b] = COLORS[index % COLORS.len()];
-    Color::Rgb(r, g, b)
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
-fn get_tmux_color(index: usize) -> String {
-    let [r, g, b] = COLORS[index % COLORS.len()];
-    format!("#{r:02x}{g:02x}{b:02x}")
-}
+/// Draws the scheduled days from `from` to `to` as an SVG image, with a
+/// timeline from `day_start` to `day_end` for each day.
+fn svg(config: &Config, schedule: &Schedule, from: NaiveDate, to: NaiveDate) -> String {
+    const PX_PER_MIN: usize = 2;
+    const ROW_HEIGHT: usize = 30;
+    const LEFT: usize = 100;
+    const TOP: usize = 20;
+
+    let first_min = config.first_hour() * 60;
+    let last_min = (config.last_hour() + 1) * 60;
+    let width = LEFT + (last_min - first_min) * PX_PER_MIN;
+    let days: Vec<_> = schedule.range(from, to).collect();
+    let height = TOP + days.len() * ROW_HEIGHT;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"sans-serif\" font-size=\"12\">\n"
+    );
+    for hour in config.first_hour()..=config.last_hour() {
+        let x = LEFT + (hour * 60 - first_min) * PX_PER_MIN;
+        out.push_str(&format!(
+            "<line x1=\"{x}\" y1=\"{TOP}\" x2=\"{x}\" y2=\"{height}\" stroke=\"#ccc\"/>\n\
+             <text x=\"{}\" y=\"{}\">{hour}</text>\n",
+            x + 2,
+            TOP - 6
+        ));
+    }
 
-fn get_dirs() -> ProjectDirs {
-    let dirs = ProjectDirs::from("com", "mistodon", "daytape").unwrap();
-    std::fs::create_dir_all(dirs.cache_dir()).unwrap();
-    std::fs::create_dir_all(dirs.config_dir()).unwrap();
-    dirs
+    for (row, (date, state)) in days.iter().enumerate() {
+        let y = TOP + row * ROW_HEIGHT;
+        out.push_str(&format!(
+            "<text x=\"4\" y=\"{}\">{date}</text>\n",
+            y + ROW_HEIGHT / 2 + 4
+        ));
+        for task in &state.tasks {
+            // Clip each task to the timeline
+            let start = task.slot.start.in_mins().clamp(first_min, last_min);
+            let end = (task.slot.start.in_mins() + task.slot.duration).clamp(first_min, last_min);
+            if end <= start {
+                continue;
+            }
+            let x = LEFT + (start - first_min) * PX_PER_MIN;
+            let w = (end - start) * PX_PER_MIN;
+            let [r, g, b] = task_color(task, config.palette());
+            let label = xml_escape(&task.label);
+            // A nested svg clips the label to its task
+            out.push_str(&format!(
+                "<svg x=\"{x}\" y=\"{}\" width=\"{w}\" height=\"{}\">\
+                 <title>{}-{} {label}</title>\
+                 <rect width=\"100%\" height=\"100%\" fill=\"#{r:02x}{g:02x}{b:02x}\"/>\
+                 <text x=\"3\" y=\"{}\" fill=\"#fff\">{label}</text></svg>\n",
+                y + 2,
+                ROW_HEIGHT - 4,
+                task.slot.start,
+                task.slot.end(),
+                ROW_HEIGHT / 2 + 2,
+            ));
+        }
+    }
+    out.push_str("</svg>\n");
+    out
 }
 
-fn target_date(now: chrono::DateTime<chrono::Local>, tomorrow: bool) -> chrono::NaiveDate {
-    let tomorrow = tomorrow || now.hour() > LAST_HOUR as u32;
-    let offset = match tomorrow {
-        true => 1,
-        _ => 0,
+fn import(
+    config: &Config,
+    main_file: &std::path::Path,
+    format: Option<ImportFormat>,
+    path: &std::path::Path,
+    dry_run: bool,
+) -> Result<()> {
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let format = format.unwrap_or(match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => ImportFormat::Csv,
+        _ => ImportFormat::Ical,
+    });
+    let source = std::fs::read_to_string(path)?;
+    let report = match format {
+        ImportFormat::Ical => schedule.import_ical(&source, config.grid_step)?,
+        ImportFormat::Csv => schedule.import_csv(&source, config.grid_step),
     };
-    (now + chrono::Duration::days(offset)).date_naive()
+    for reason in &report.skipped {
+        eprintln!("Skipped: {reason}");
+    }
+    for change in &report.sanitized {
+        eprintln!("Removed control characters: {change}");
+    }
+
+    if dry_run {
+        return preview(main_file, &schedule);
+    }
+    save_schedule(main_file, &schedule, config.backups)?;
+    match report.skipped.len() {
+        0 => println!("Imported {} tasks", report.imported),
+        skipped => println!("Imported {} tasks, skipped {skipped}", report.imported),
+    }
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn add(
+    config: &Config,
+    main_file: &std::path::Path,
+    label: String,
+    slot: TimeSlot,
+    date: Option<NaiveDate>,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let target_date = date_or_today(date);
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let sanitized = daytape::sanitize_label(&label);
+    if sanitized != label {
+        eprintln!("Removed control characters: {label:?} became {sanitized:?}");
+    }
+    let label = sanitized;
 
-    match args.sub {
-        Some(SubCommand::Edit { tomorrow }) => edit(tomorrow),
-        Some(SubCommand::Show { show_args }) => tmux(&show_args),
-        None => tmux(&args.show_args),
+    if slot.duration > DAY_MINS {
+        bail!("duration {} is longer than a day", slot.duration);
     }
-}
 
-fn load_schedule(path: &std::path::Path) -> Result<Schedule> {
-    let source = std::fs::read_to_string(path)?;
-    let schedule: Schedule = serde_yaml::from_str(&source)?;
-    Ok(schedule)
-}
+    // Snap to the editor's grid so the task can be edited there afterwards,
+    // rounding down where rounding up would wrap to the start of the day
+    let step = config.grid_step;
+    let slot = TimeSlot {
+        start: slot
+            .start
+            .round_to_nearest(step)
+            .max(slot.start.floor_to(step)),
+        duration: ((slot.duration + step / 2) / step).max(1) * step,
+    };
 
-fn tmux(show_args: &ShowArgs) -> Result<()> {
-    let now = chrono::Local::now();
-    let target_date = target_date(now, show_args.tomorrow);
+    let carried = schedule.carried_over(target_date);
+    let state = schedule
+        .dates
+        .entry(target_date)
+        .or_insert_with(|| DayState {
+            date: target_date,
+            tasks: vec![],
+        });
+    if !force {
+        let mut others = state.tasks.iter().chain(&carried);
+        if let Some(other) = others.find(|task| task.slot.overlaps(&slot)) {
+            bail!(
+                "{} overlaps {} ({}-{}), use --force to add it anyway",
+                label,
+                other.label,
+                other.slot.start,
+                other.slot.end()
+            );
+        }
+    }
 
-    let dirs = get_dirs();
-    let mut main_file = dirs.config_dir().to_owned();
-    main_file.push("daytape.yaml");
+    state.tasks.push(Task::new(slot, label.as_str()));
+    state.tasks.sort();
+    if dry_run {
+        return preview(main_file, &schedule);
+    }
+    save_schedule(main_file, &schedule, config.backups)?;
+
+    println!(
+        "Added {label} ({}-{}) on {target_date}",
+        slot.start,
+        slot.end()
+    );
+    Ok(())
+}
 
-    let schedule: Schedule = load_schedule(&main_file).unwrap_or(Schedule::default());
-    let state = schedule.dates.get(&target_date);
+fn remove(
+    config: &Config,
+    main_file: &std::path::Path,
+    at: Option<Time>,
+    label: Option<String>,
+    date: Option<NaiveDate>,
+    dry_run: bool,
+) -> Result<()> {
+    let target_date = date_or_today(date);
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let matches = |task: &Task| match (at, &label) {
+        (Some(at), _) => task.slot.contains(at),
+        (None, Some(label)) => &task.label == label,
+        (None, None) => false,
+    };
 
-    let width = show_args.width as usize;
+    let state = schedule.dates.get_mut(&target_date);
+    let removed: Vec<Task> = state
+        .iter()
+        .flat_map(|state| &state.tasks)
+        .filter(|task| matches(task))
+        .cloned()
+        .collect();
+    if removed.is_empty() {
+        match (at, label) {
+            (Some(at), _) => bail!("No task at {at} on {target_date}"),
+            (None, label) => bail!(
+                "No task labelled {:?} on {target_date}",
+                label.unwrap_or_default()
+            ),
+        }
+    }
 
-    if state.is_none() {
-        print!(
-            "#[bg=red]{: <width$}#[bg=default]",
-            "No task set",
-            width = width
+    if let Some(state) = state {
+        state.tasks.retain(|task| !matches(task));
+    }
+    if dry_run {
+        return preview(main_file, &schedule);
+    }
+    save_schedule(main_file, &schedule, config.backups)?;
+
+    for task in removed {
+        println!(
+            "Removed {} ({}-{}) on {target_date}",
+            task.label,
+            task.slot.start,
+            task.slot.end()
         );
-        return Ok(());
     }
-    let state = state.unwrap();
+    Ok(())
+}
+
+/// Copies `from`'s tasks over to `to`, unless `to` already has tasks and
+/// `force` isn't set.
+fn copy_day(
+    schedule: &mut Schedule,
+    from: &DayState,
+    to: NaiveDate,
+    force: bool,
+) -> std::result::Result<usize, String> {
+    let occupied = schedule
+        .dates
+        .get(&to)
+        .is_some_and(|state| !state.tasks.is_empty());
+    if occupied && !force {
+        return Err(format!("{to} already has tasks"));
+    }
+    schedule.dates.insert(to, from.copy_to(to));
+    Ok(from.tasks.len())
+}
+
+fn copy(
+    config: &Config,
+    main_file: &std::path::Path,
+    from: NaiveDate,
+    to: NaiveDate,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let source = match schedule.dates.get(&from) {
+        Some(state) if !state.tasks.is_empty() => state.clone(),
+        _ => bail!("No tasks on {from}"),
+    };
+    let count = match copy_day(&mut schedule, &source, to, force) {
+        Ok(count) => count,
+        Err(e) => bail!("{e} (pass --force to replace them)"),
+    };
+    if dry_run {
+        return preview(main_file, &schedule);
+    }
+    save_schedule(main_file, &schedule, config.backups)?;
 
-    let mut to_display = String::new();
+    println!("Copied {count} tasks from {from} to {to}");
+    Ok(())
+}
 
-    let mut task = None;
-    let mut run = 0;
+fn clear(
+    config: &Config,
+    main_file: &std::path::Path,
+    date: Option<NaiveDate>,
+    dry_run: bool,
+) -> Result<()> {
+    let target_date = date_or_today(date);
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let count = match schedule.dates.remove(&target_date) {
+        Some(state) if !state.tasks.is_empty() => state.tasks.len(),
+        _ => bail!("No tasks on {target_date}"),
+    };
+    if dry_run {
+        return preview(main_file, &schedule);
+    }
+    save_schedule(main_file, &schedule, config.backups)?;
 
-    let mut time = Time::new(now.hour() as usize, now.minute() as usize);
-    for _ in 0..width {
-        use std::fmt::Write;
+    println!("Removed {count} tasks on {target_date}");
+    Ok(())
+}
 
-        let current_task = state.tasks.iter().find(|task| task.slot.contains(time));
-        if current_task == task {
-            run += 1;
-        } else {
-            task = current_task;
-            run = 0;
-            let color = match task {
-                Some(task) => get_tmux_color(get_color_index(&task.label)),
-                None => "default".to_owned(),
-            };
-            write!(&mut to_display, "#[bg={color}]").unwrap();
+fn apply_template(
+    config: &Config,
+    main_file: &std::path::Path,
+    name: &str,
+    date: Option<NaiveDate>,
+) -> Result<()> {
+    let target_date = date_or_today(date);
+    let mut schedule: Schedule = load_schedule(main_file)?;
+
+    let Some(template) = schedule.templates.get(name).cloned() else {
+        let mut names: Vec<&String> = schedule.templates.keys().collect();
+        names.sort();
+        match names.is_empty() {
+            true => bail!("No template named {name:?}"),
+            false => bail!(
+                "No template named {name:?} (expected one of {})",
+                names
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
+    };
 
-        let ch = task
-            .and_then(|task| task.label.chars().nth(run))
-            .unwrap_or(' ');
-        write!(&mut to_display, "{ch}").unwrap();
-
-        time += Time::mins(1);
+    let state = schedule
+        .dates
+        .entry(target_date)
+        .or_insert_with(|| DayState {
+            date: target_date,
+            tasks: vec![],
+        });
+    let before = state.tasks.clone();
+    state.merge_tasks(&template);
+    let added: Vec<Task> = state
+        .tasks
+        .iter()
+        .filter(|task| !before.contains(task))
+        .cloned()
+        .collect();
+    let is_empty = state.tasks.is_empty();
+    if is_empty {
+        schedule.dates.remove(&target_date);
     }
+    save_schedule(main_file, &schedule, config.backups)?;
+
+    for task in &template {
+        let [start, end] = [task.slot.start, task.slot.end()];
+        match added.contains(task) {
+            true => println!("Added {} ({start}-{end}) on {target_date}", task.label),
+            false => println!(
+                "Skipped {} ({start}-{end}), which overlaps another task",
+                task.label
+            ),
+        }
+    }
+    Ok(())
+}
 
-    print!("{to_display}#[bg=default]");
+/// Why saving `state` from the editor needs confirming, if it does: the
+/// first two of its tasks that overlap, counting those `carried` over into it.
+fn save_warning(state: &DayState, carried: &[Task]) -> Option<String> {
+    state
+        .conflicts_with(carried)
+        .first()
+        .map(|(a, b)| format!("{} overlaps {}", a.label, b.label))
+}
 
-    Ok(())
+/// Where the editor's grid starts and how wide each of its cells is, on a
+/// terminal `width` columns wide. The hour labels are narrowed when the grid
+/// wouldn't otherwise fit, then the cells stretch across the rest of the width.
+fn grid_layout(width: usize, cells_per_hour: usize) -> [usize; 2] {
+    let ox = if width < 6 + cells_per_hour + 1 { 3 } else { 6 };
+    [
+        ox,
+        std::cmp::max(1, width.saturating_sub(ox + 1) / cells_per_hour),
+    ]
+}
+
+/// The number of hours to scroll past so that the cursor's row is on screen,
+/// scrolling as little as possible from `scroll`.
+fn follow_cursor(
+    scroll: usize,
+    cursor_row: usize,
+    visible_hours: usize,
+    total_hours: usize,
+) -> usize {
+    let scroll = if cursor_row < scroll {
+        cursor_row
+    } else if cursor_row >= scroll + visible_hours {
+        cursor_row + 1 - visible_hours
+    } else {
+        scroll
+    };
+    std::cmp::min(scroll, total_hours - visible_hours)
 }
 
-fn edit(tomorrow: bool) -> Result<()> {
+fn edit(
+    config: &Config,
+    main_file: &std::path::Path,
+    date: Option<NaiveDate>,
+    tomorrow: bool,
+) -> Result<()> {
     use std::time::{Duration, Instant};
-    use termbuffer::{char, App, Draw, Event, Key};
+    use termbuffer::{char, App, Draw, Event, Key, MouseButton, MouseEvent};
 
+    let color = use_color();
     let solid_text_color = Color::Rgb(240, 240, 240);
     let text_color = solid_text_color;
-    let sel_color = Color::Rgb(190, 150, 255);
+    // Without color, selection is shown as dark text on a light background
+    let sel_color = match color {
+        true => Color::Rgb(190, 150, 255),
+        false => solid_text_color,
+    };
+    let sel_text_color = match color {
+        true => solid_text_color,
+        false => Color::Rgb(0, 0, 0),
+    };
 
     let now = chrono::Local::now();
-    let today = now.date_naive();
-    let target_date = target_date(now, tomorrow);
-
-    let dirs = get_dirs();
-    let mut main_file = dirs.config_dir().to_owned();
-    main_file.push("daytape.yaml");
+    let target_date = target_date(config, now, date, tomorrow);
+    let [first_hour, last_hour] = [config.first_hour(), config.last_hour()];
 
-    let mut schedule: Schedule = load_schedule(&main_file).unwrap_or(Schedule::default());
+    let mut schedule: Schedule = load_schedule(main_file)?;
 
     let delay = Duration::from_millis(1000 / 60);
 
@@ -201,11 +2014,25 @@ fn edit(tomorrow: bool) -> Result<()> {
             date: target_date.clone(),
             tasks: vec![],
         });
+    // Tasks from the day before that run past midnight, which are shown but
+    // can't be edited from here
+    let carried = schedule.carried_over(target_date);
 
-    let mut cursor: Time = DAY_START;
+    let grid_step = config.grid_step;
+    let cells_per_hour = 60 / grid_step;
+
+    let mut cursor: Time = load_cursors()
+        .get(&target_date)
+        .copied()
+        .filter(|cursor| cursor.is_between_inclusive(config.day_start, config.day_end))
+        .unwrap_or(config.day_start);
 
     fn drawtext(d: &mut Draw, text: &str, from: [usize; 2], max_x: usize, fg: Color, bg: Color) {
         let [x, y] = from;
+        if y >= d.rows() {
+            return;
+        }
+        let max_x = std::cmp::min(max_x, d.columns().saturating_sub(1));
         for (i, ch) in text.chars().enumerate() {
             let x = x + i;
             if x > max_x {
@@ -215,36 +2042,123 @@ fn edit(tomorrow: bool) -> Result<()> {
         }
     }
 
+    const UNDO_LIMIT: usize = 100;
+
+    // Where the grid is drawn, and the width in characters of each step of
+    // it, both fitted to the terminal as of the last frame
+    let [mut ox, oy] = [6, 2];
+    let mut cell_width = 1;
+
     let mut typed = String::new();
+    // The number of hours scrolled past at the top of the grid
+    let mut scroll = 0;
+    // How many hours fit on screen, as of the last frame
+    let mut visible_hours = 0;
+    // The start of the task whose end is being dragged with the mouse
+    let mut dragging: Option<Time> = None;
     let mut cmd_mode = false;
+    // Either ':' for a command or '/' for a search
+    let mut prompt = ':';
+    let mut command = String::new();
+    let mut last_search: Option<String> = None;
+    let mut help_open = false;
+    // With vim keys, typing only edits labels in insert mode
+    let mut insert_mode = !config.vim_keys;
+    let mut note_mode = false;
+    let mut undo_stack: Vec<DayState> = vec![];
+    let mut redo_stack: Vec<DayState> = vec![];
+    let mut saved_tasks = state.tasks.clone();
+    // A command that has asked to be entered again before it runs
+    let mut confirm: Option<String> = None;
+    let mut message: Option<String> = None;
 
     loop {
         let mut quit = false;
         let mut save = false;
         let mut scale_up = false;
         let mut scale_down = false;
+        let mut extend = false;
+        let mut move_earlier = false;
+        let mut move_later = false;
         let mut delete = false;
+        let mut clear = false;
+        let mut toggle_done = false;
+        let mut cycle_color = false;
+        let mut load_template = false;
+        let mut duplicate = false;
+        let mut undo = false;
+        let mut redo = false;
         let mut backspace = false;
+        let mut entered: Option<String> = None;
+        let mut searched: Option<String> = None;
+        let mut retime: Option<TimeSlot> = None;
+        // Whether to leave the cursor at the end of a retimed task
+        let mut cursor_to_end = false;
+        let mut copy_to: Option<(NaiveDate, bool)> = None;
+        let mut note_typed = String::new();
+        let mut note_backspace = false;
 
         let start_at = Instant::now();
 
-        let selected_slot = state
-            .tasks
-            .iter()
-            .map(|task| task.slot)
-            .find(|slot| slot.contains(cursor));
+        let selected_slot = state.task_at(cursor).map(|task| task.slot);
 
-        const VALID_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_,.;'\"/|()0123456789!?<>~+=*&^%$#@ ";
+        // The time of the grid cell at a (1-based) mouse position, if any
+        let top_hour = first_hour + scroll;
+        let cell_at = |x: u16, y: u16| {
+            let [x, y] = [x, y].map(|n| (n as usize).saturating_sub(1));
+            let row = y.checked_sub(oy).filter(|&row| row < visible_hours)?;
+            let cell = (x.checked_sub(ox)? / cell_width) * grid_step;
+            (cell < 60).then(|| Time::new(top_hour + row, cell))
+        };
+        let mut clicked: Option<Time> = None;
+        let mut dragged_to: Option<Time> = None;
+        let dirty = state.tasks != saved_tasks;
+
+        const VALID_CHARS: &str =
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_,.;'\"/|()0123456789!?<>~+=*&^%$#@ ";
 
         for event in app.events() {
-            match event.unwrap() {
+            message = None;
+
+            let normal_mode = !(insert_mode || cmd_mode || note_mode);
+            let event = match event.unwrap() {
+                Event::Key(Key::Char(c)) if normal_mode => Event::Key(
+                    VIM_KEYS
+                        .iter()
+                        .find(|&&(key, _)| key == c)
+                        .map_or(Key::Char(c), |&(_, key)| key),
+                ),
+                event => event,
+            };
+
+            match event {
+                _ if help_open => help_open = false,
+                Event::Key(Key::Esc) if note_mode => note_mode = false,
+                Event::Key(Key::Backspace) if note_mode => note_backspace = true,
+                Event::Key(Key::Char(c)) if note_mode => note_typed.push(c),
+                _ if note_mode => (),
+                Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => clicked = cell_at(x, y),
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, ..))
+                    if cursor.hour > first_hour =>
+                {
+                    cursor -= Time::hours(1)
+                }
+                Event::Mouse(MouseEvent::Press(MouseButton::WheelDown, ..))
+                    if cursor.hour < last_hour =>
+                {
+                    cursor += Time::hours(1)
+                }
+                Event::Mouse(MouseEvent::Hold(x, y)) if dragging.is_some() => {
+                    dragged_to = cell_at(x, y).or(dragged_to)
+                }
+                Event::Mouse(MouseEvent::Release(..)) => dragging = None,
                 Event::Key(Key::Left) => match selected_slot {
-                    Some(slot) => cursor = slot.start - Time::mins(5),
-                    None => cursor -= Time::mins(5),
+                    Some(slot) => cursor = slot.start - Time::mins(grid_step),
+                    None => cursor -= Time::mins(grid_step),
                 },
                 Event::Key(Key::Right) => match selected_slot {
-                    Some(slot) => cursor = slot.end(),
-                    None => cursor += Time::mins(5),
+                    Some(slot) => cursor = slot.end_within_day(),
+                    None => cursor += Time::mins(grid_step),
                 },
                 Event::Key(Key::Up) => cursor -= Time::hours(1),
                 Event::Key(Key::Down) => {
@@ -252,130 +2166,519 @@ fn edit(tomorrow: bool) -> Result<()> {
                         cursor += Time::hours(1);
                     }
                 }
-                Event::Key(Key::Backspace) => backspace = true,
-                Event::Key(Key::Esc) => cmd_mode = false,
-                Event::Key(Key::Char(c)) if cmd_mode => {
+                // Backspacing past the start of the line leaves it
+                Event::Key(Key::Backspace) if cmd_mode => cmd_mode = command.pop().is_some(),
+                Event::Key(Key::Backspace) if insert_mode => backspace = true,
+                Event::Key(Key::Ctrl('r')) => redo = true,
+                Event::Key(Key::Alt('<')) => move_earlier = true,
+                Event::Key(Key::Alt('>')) => move_later = true,
+                Event::Key(Key::Esc) if cmd_mode => {
                     cmd_mode = false;
-                    match c {
-                        'q' => {
-                            save = true;
-                            quit = true;
-                        }
-                        's' => save = true,
-                        'd' => delete = true,
-                        'x' => quit = true,
-                        _ => (),
+                    command.clear();
+                }
+                Event::Key(Key::Esc) => insert_mode = !config.vim_keys,
+                Event::Key(Key::Char('\n')) if cmd_mode => {
+                    cmd_mode = false;
+                    let line = std::mem::take(&mut command);
+                    // A command starting with '/' searches, for when '/' is
+                    // typed into labels
+                    if prompt == '/' {
+                        searched = Some(line);
+                    } else if let Some(query) = line.strip_prefix('/') {
+                        searched = Some(query.trim().to_owned());
+                    } else {
+                        entered = Some(line);
                     }
                 }
+                Event::Key(Key::Char(c)) if cmd_mode => command.push(c),
                 Event::Key(Key::Char(c)) => match c {
-                    '[' => scale_down = true,
-                    ']' => scale_up = true,
-                    ':' => cmd_mode = !cmd_mode,
-                    ' ' => typed.push(' '),
-                    ch if VALID_CHARS.contains(ch) => typed.push(ch),
+                    c if c == config.keys.scale_down => scale_down = true,
+                    c if c == config.keys.scale_up => scale_up = true,
+                    c if c == config.keys.extend => extend = true,
+                    // While typing, these need Alt so they can go in labels
+                    '<' if !insert_mode => move_earlier = true,
+                    '>' if !insert_mode => move_later = true,
+                    '/' if !insert_mode => {
+                        cmd_mode = true;
+                        prompt = c;
+                    }
+                    c if c == config.keys.command => {
+                        cmd_mode = true;
+                        prompt = c;
+                    }
+                    '?' if !insert_mode => help_open = true,
+                    'i' if !insert_mode => insert_mode = true,
+                    ' ' if insert_mode => typed.push(' '),
+                    ch if insert_mode && VALID_CHARS.contains(ch) => typed.push(ch),
                     _ => (),
                 },
                 _ => (),
             }
         }
 
-        cursor = cursor.clamp(DAY_START, DAY_END);
+        if let Some(line) = entered {
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let args: Vec<&str> = words.collect();
+            let confirming = std::mem::take(&mut confirm).as_deref() == Some(name);
+            let conflict = save_warning(&state, &carried);
+            let keys = &config.keys;
+            let bound = |key: char| name.chars().eq([key]);
+            let action = match name {
+                _ if bound(keys.save) => "save",
+                _ if bound(keys.quit) => "quit",
+                _ if bound(keys.delete) => "delete",
+                name => name,
+            };
+            match action {
+                "save" | "quit" if conflict.is_some() && !confirming => {
+                    message = conflict.map(|conflict| {
+                        format!("{conflict} - {}{name} again to save anyway", keys.command)
+                    });
+                    confirm = Some(name.to_owned());
+                }
+                "quit" => {
+                    save = true;
+                    quit = true;
+                }
+                "save" => save = true,
+                "delete" => delete = true,
+                "c" => toggle_done = true,
+                "p" => cycle_color = true,
+                "n" => note_mode = selected_slot.is_some(),
+                "l" => load_template = true,
+                "dup" => duplicate = true,
+                "?" => help_open = true,
+                "u" => undo = true,
+                "r" => redo = true,
+                "t" => match parse_slot(&args, grid_step) {
+                    Ok(slot) => retime = Some(slot),
+                    Err(e) => message = Some(e),
+                },
+                "dur" => match (selected_slot, parse_duration(&args, grid_step)) {
+                    (None, _) => message = Some("no task selected".to_owned()),
+                    (_, Err(e)) => message = Some(e),
+                    (Some(slot), Ok(duration)) => {
+                        retime = Some(TimeSlot {
+                            start: slot.start,
+                            duration,
+                        });
+                        cursor_to_end = true;
+                    }
+                },
+                "copy" | "copy!" => match args[..] {
+                    [date] => match date.parse::<NaiveDate>() {
+                        Ok(date) if date == target_date => {
+                            message = Some(format!("already editing {date}"))
+                        }
+                        Ok(date) => copy_to = Some((date, name == "copy!")),
+                        Err(e) => message = Some(format!("{date}: {e}")),
+                    },
+                    _ => message = Some("usage: :copy DATE".to_owned()),
+                },
+                "clear" if !state.tasks.is_empty() && !confirming => {
+                    confirm = Some(name.to_owned());
+                    message = Some(format!(
+                        "clear every task? {}clear again to confirm",
+                        keys.command
+                    ));
+                }
+                "clear" => clear = true,
+                "x" if dirty && !confirming => {
+                    confirm = Some(name.to_owned());
+                    message = Some(format!(
+                        "unsaved changes - {}x again to discard",
+                        keys.command
+                    ));
+                }
+                "x" => quit = true,
+                "" => (),
+                _ => message = Some(format!("unknown command {}{name}", keys.command)),
+            }
+        }
+
+        if let Some(query) = searched {
+            // An empty search repeats the last one, moving on to the next match
+            let from = match query.is_empty() {
+                true => Some(cursor),
+                false => None,
+            };
+            if !query.is_empty() {
+                last_search = Some(query.to_lowercase());
+            }
+            let matches: Vec<Time> = match &last_search {
+                Some(query) => state
+                    .tasks
+                    .iter()
+                    .filter(|task| task.label.to_lowercase().contains(query.as_str()))
+                    .map(|task| task.slot.start)
+                    .collect(),
+                None => vec![],
+            };
+            let next = matches
+                .iter()
+                .find(|&&start| from.is_none_or(|from| start > from))
+                .or(matches.first());
+            match next {
+                Some(&start) => cursor = start,
+                None => message = Some("no match".to_owned()),
+            }
+        }
+
+        cursor = cursor.clamp(config.day_start, config.day_end);
 
         if save {
-            schedule.dates.retain(|date, _| date >= &today);
-            schedule.dates.insert(target_date, state.clone());
-            let output = serde_yaml::to_string(&schedule)?;
-            std::fs::write(&main_file, &output)?;
+            // The editor may have been open since before midnight
+            if let Some(date) = config.keep_from(chrono::Local::now().date_naive()) {
+                schedule.prune_before(date);
+            }
+            match state.tasks.is_empty() {
+                true => schedule.dates.remove(&target_date),
+                false => schedule.dates.insert(target_date, state.clone()),
+            };
+            save_schedule(main_file, &schedule, config.backups)?;
+            saved_tasks = state.tasks.clone();
+        }
+
+        if let Some((to, force)) = copy_to {
+            message = Some(match copy_day(&mut schedule, &state, to, force) {
+                Ok(count) => {
+                    save_schedule(main_file, &schedule, config.backups)?;
+                    format!("copied {count} tasks to {to}")
+                }
+                Err(e) => format!("{e} - :copy! to replace them"),
+            });
         }
 
         if quit {
+            // Losing the cursor position isn't worth failing over
+            let _ = save_cursor(target_date, cursor, now.date_naive());
             return Ok(());
         }
 
+        if undo {
+            if let Some(previous) = undo_stack.pop() {
+                redo_stack.push(std::mem::replace(&mut state, previous));
+            }
+        }
+        if redo {
+            if let Some(next) = redo_stack.pop() {
+                undo_stack.push(std::mem::replace(&mut state, next));
+            }
+        }
+
+        // Clicking the last cell of a task grabs its end, to drag it
+        if let Some(time) = clicked {
+            cursor = time;
+            dragging = state
+                .task_at(time)
+                .filter(|task| task.slot.end_within_day() - Time::mins(grid_step) == time)
+                .map(|task| task.slot.start);
+        }
+
+        let before = state.clone();
+
+        // The time from the end of a slot to the end of the day's last cell
+        let rest_of_day = |slot: TimeSlot| TimeSlot {
+            start: slot.end(),
+            duration: (config.day_end.in_mins() + grid_step)
+                .saturating_sub(slot.start.in_mins() + slot.duration),
+        };
+        // Whether a slot starts and ends within the day's cells, so none of it
+        // runs off the grid
+        let within_day = |slot: TimeSlot| {
+            slot.start >= config.day_start
+                && slot.start.in_mins() + slot.duration <= config.day_end.in_mins() + grid_step
+        };
+
+        if let Some((start, to)) = dragging.zip(dragged_to.map(|to| to.min(config.day_end))) {
+            let selected = state.tasks.iter().position(|task| task.slot.start == start);
+            let slot = TimeSlot {
+                start,
+                duration: (start.minutes_until(to).max(0) as usize) + grid_step,
+            };
+            let clash = state
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != selected)
+                .map(|(_, task)| task)
+                .chain(&carried)
+                .any(|task| task.slot.overlaps(&slot));
+            if let Some(i) = selected.filter(|_| !clash) {
+                state.tasks[i].slot = slot;
+                cursor = slot.end_within_day() - Time::mins(grid_step);
+            }
+        }
+
         if delete {
             state.tasks.retain(|task| !task.slot.contains(cursor));
         }
 
+        if clear {
+            state.tasks.clear();
+        }
+
+        if load_template {
+            if let Some(template) = schedule.template_for(target_date) {
+                state.merge_tasks(template);
+            }
+        }
+
+        if let Some(slot) = retime {
+            let selected = state
+                .tasks
+                .iter()
+                .position(|task| task.slot.contains(cursor));
+            let clash = state
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != selected)
+                .map(|(_, task)| task)
+                .chain(&carried)
+                .find(|task| task.slot.overlaps(&slot));
+            match (selected, clash) {
+                (None, _) => message = Some("no task selected".to_owned()),
+                (_, Some(other)) => message = Some(format!("would overlap {}", other.label)),
+                (Some(_), None) if !within_day(slot) => {
+                    message = Some(format!(
+                        "must be within {}-{}",
+                        config.day_start,
+                        config.day_end + Time::mins(grid_step)
+                    ))
+                }
+                (Some(i), None) => {
+                    state.tasks[i].slot = slot;
+                    state.tasks.sort();
+                    cursor = match cursor_to_end {
+                        true => slot.end_within_day() - Time::mins(grid_step),
+                        false => slot.start,
+                    };
+                }
+            }
+        }
+
+        if duplicate {
+            match state.task_at(cursor).cloned() {
+                None => message = Some("no task selected".to_owned()),
+                Some(mut copy) => {
+                    // Into the first gap after the task that fits it, or
+                    // straight after it, overlapping whatever is there
+                    let rest_of_day = rest_of_day(copy.slot);
+                    let gap = state
+                        .gaps(rest_of_day)
+                        .into_iter()
+                        .find(|gap| gap.duration >= copy.slot.duration);
+                    copy.slot.start = gap.map_or(rest_of_day.start, |gap| gap.start);
+                    copy.done = false;
+                    cursor = copy.slot.start;
+                    state.tasks.push(copy);
+                    state.tasks.sort();
+                }
+            }
+        }
+
+        if extend {
+            match state.index_at(cursor) {
+                None => message = Some("no task selected".to_owned()),
+                Some(i) => {
+                    let rest_of_day = rest_of_day(state.tasks[i].slot);
+                    let gap = state
+                        .gaps(rest_of_day)
+                        .into_iter()
+                        .next()
+                        .filter(|gap| gap.start == rest_of_day.start);
+                    match gap {
+                        None => message = Some("no free time after the task".to_owned()),
+                        Some(gap) => {
+                            let task = &mut state.tasks[i];
+                            task.slot.duration += gap.duration;
+                            cursor = task.slot.end_within_day() - Time::mins(grid_step);
+                        }
+                    }
+                }
+            }
+        }
+
+        if move_earlier || move_later {
+            if let Some(i) = state.tasks.iter().position(|t| t.slot.contains(cursor)) {
+                let slot = state.tasks[i].slot;
+                // Counted in minutes, as moving a `Time` earlier than midnight
+                // would wrap around to the end of the day
+                let moved = match move_earlier {
+                    true => slot.start.in_mins().checked_sub(grid_step),
+                    false => Some(slot.start.in_mins() + grid_step),
+                }
+                .map(|start| TimeSlot {
+                    start: Time::mins(start),
+                    ..slot
+                })
+                .filter(|&slot| within_day(slot));
+                let clash = moved.and_then(|slot| {
+                    state
+                        .tasks
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, task)| task)
+                        .chain(&carried)
+                        .find(|task| task.slot.overlaps(&slot))
+                });
+                match (moved, clash) {
+                    (None, _) => message = Some("can't move outside the day".to_owned()),
+                    (_, Some(other)) => message = Some(format!("blocked by {}", other.label)),
+                    (Some(slot), None) => {
+                        state.tasks[i].slot = slot;
+                        state.tasks.sort();
+                        cursor = match move_earlier {
+                            true => cursor - Time::mins(grid_step),
+                            false => cursor + Time::mins(grid_step),
+                        };
+                    }
+                }
+            }
+        }
+
         let create_if_empty = !typed.is_empty();
-        if create_if_empty && !state.tasks.iter().any(|task| task.slot.contains(cursor)) {
-            state.tasks.push(Task {
-                slot: TimeSlot {
+        if create_if_empty && state.task_at(cursor).is_none() {
+            state.tasks.push(Task::new(
+                TimeSlot {
                     start: cursor,
-                    duration: 15,
+                    duration: config.default_duration(),
                 },
-                label: String::new(),
-            });
+                "",
+            ));
             state.tasks.sort();
         }
 
-        let selected_task = state
-            .tasks
-            .iter_mut()
-            .find(|task| task.slot.contains(cursor));
-        if let Some(task) = selected_task {
+        if let Some(task) = state.task_at_mut(cursor) {
             task.label.push_str(&typed);
             typed.clear();
 
             if backspace {
                 task.label.pop();
             }
-            if scale_up {
-                task.slot.duration += 5;
+            if toggle_done {
+                task.toggle_done();
+            }
+            if cycle_color {
+                cycle_task_color(task, config.palette());
+            }
+            if note_mode {
+                let notes = task.notes.get_or_insert_with(String::new);
+                notes.push_str(&note_typed);
+                if note_backspace {
+                    notes.pop();
+                }
+            }
+            if task.notes.as_deref() == Some("") {
+                task.notes = None;
+            }
+            let grown = TimeSlot {
+                duration: task.slot.duration + grid_step,
+                ..task.slot
+            };
+            if scale_up && within_day(grown) {
+                task.slot = grown;
             }
             if scale_down {
-                if task.slot.duration > 5 {
-                    task.slot.duration -= 5;
+                if task.slot.duration > grid_step {
+                    task.slot.duration -= grid_step;
                 }
             }
             if scale_up || scale_down {
-                cursor = task.slot.end() - Time::mins(5);
+                cursor = task.slot.end_within_day() - Time::mins(grid_step);
             }
         }
 
+        if state.tasks != before.tasks {
+            undo_stack.push(before);
+            if undo_stack.len() > UNDO_LIMIT {
+                undo_stack.remove(0);
+            }
+            redo_stack.clear();
+        }
+
         {
             let mut draw = app.draw();
             let draw = &mut draw;
-            let [_w, _h] = [draw.columns(), draw.rows()];
-            let date = target_date.to_string();
-            drawtext(draw, &date, [0, 0], 10, text_color, Color::Default);
+            let [w, h] = [draw.columns(), draw.rows()];
+
+            [ox, cell_width] = grid_layout(w, cells_per_hour);
+            let compact = ox < 6;
+
+            // Leave room below the grid for a blank line, the command line,
+            // its docs and the status line
+            let total_hours = last_hour - first_hour + 1;
+            visible_hours = std::cmp::min(total_hours, h.saturating_sub(oy + 4).max(1));
+            let cursor_row = cursor.hour - first_hour;
+            scroll = follow_cursor(scroll, cursor_row, visible_hours, total_hours);
+
+            let top_hour = first_hour + scroll;
+            let row_of = |hour: usize| {
+                (top_hour..top_hour + visible_hours)
+                    .contains(&hour)
+                    .then(|| oy + hour - top_hour)
+            };
+            let pane_y = oy + visible_hours + 1;
+
+            let mut title = match state.tasks != saved_tasks {
+                true => format!("{target_date} *"),
+                false => target_date.to_string(),
+            };
+            if config.vim_keys {
+                let mode = match insert_mode {
+                    true => "-- INSERT --",
+                    false => "-- NORMAL --",
+                };
+                title = format!("{title: <12}  {mode}");
+            }
+            drawtext(draw, &title, [0, 0], w, text_color, Color::Default);
+            if let Some(message) = &message {
+                let x = title.chars().count() + 2;
+                drawtext(draw, message, [x, 0], w, sel_color, Color::Default);
+            }
 
-            let text_color = match cmd_mode {
+            let text_color = match cmd_mode && color {
                 false => text_color,
                 true => Color::Rgb(140, 140, 140),
             };
 
-            drawtext(
-                draw,
-                "time ||  .  .  |  .  .  |  .  .  |  .  .  |",
-                [0, 1],
-                99,
-                text_color,
-                Color::Default,
-            );
-            for (i, hour) in (FIRST_HOUR..=LAST_HOUR).enumerate() {
-                drawtext(
-                    draw,
-                    &format!("{hour: >4} |"),
-                    [0, i + 2],
-                    6,
-                    text_color,
-                    Color::Default,
-                );
+            let mut header = String::from(if compact { "  |" } else { "time |" });
+            for min in (0..60).step_by(grid_step) {
+                header.push(if min % 15 == 0 { '|' } else { '.' });
+                header.push_str(&" ".repeat(cell_width - 1));
+            }
+            header.push('|');
+            drawtext(draw, &header, [0, 1], w, text_color, Color::Default);
+            for (i, hour) in (top_hour..top_hour + visible_hours).enumerate() {
+                let label = match compact {
+                    true => format!("{hour: >2}|"),
+                    false => format!("{hour: >4} |"),
+                };
+                drawtext(draw, &label, [0, oy + i], ox, text_color, Color::Default);
+            }
+
+            let [cx, _] = cursor.to_grid(grid_step);
+            let cx = ox + cx * cell_width;
+            if let Some(cy) = row_of(cursor.hour).filter(|&cy| cy < h && cx < w) {
+                draw.set(cy, cx, char!(' ', Color::Default, sel_color));
             }
 
-            let [ox, oy] = [6, 2];
-            let [cx, cy] = cursor.to_grid();
-            let cx = ox + cx * 3;
-            let cy = oy + cy - FIRST_HOUR;
+            let mut shown = state.tasks.clone();
+            if config.distinct_colors {
+                assign_distinct_colors(&mut shown, config.palette());
+            }
 
-            draw.set(cy, cx, char!(' ', Color::Default, sel_color));
+            let selected = |task: &Task| !carried.contains(task) && task.slot.contains(cursor);
+            let carried_text_color = Color::Rgb(140, 140, 140);
 
-            let max_width = 36;
-            for task in &state.tasks {
-                let [x, y] = task.slot.start.to_grid();
-                let mut x = ox + x * 3;
-                let mut y = oy + y - FIRST_HOUR;
-                let mut label_width = (task.slot.duration / 5) * 3;
+            let max_width = cells_per_hour * cell_width;
+            for task in carried.iter().chain(&shown) {
+                let [x, mut hour] = task.slot.start.to_grid(grid_step);
+                let mut x = ox + x * cell_width;
+                let mut label_width = task.slot.duration.div_ceil(grid_step) * cell_width;
 
                 while label_width > 0 {
                     let usable_width = std::cmp::min(label_width, max_width - (x - ox));
@@ -383,28 +2686,139 @@ fn edit(tomorrow: bool) -> Result<()> {
 
                     let label = format!("{: <1$}", &task.label, usable_width);
 
-                    let color = get_edit_color(get_color_index(&task.label));
-                    let color = if task.slot.contains(cursor) {
-                        sel_color
+                    let [fg, bg] = if selected(task) {
+                        [sel_text_color, sel_color]
+                    } else if color {
+                        [
+                            match carried.contains(task) {
+                                true => carried_text_color,
+                                false => solid_text_color,
+                            },
+                            get_edit_color(task_color(task, config.palette())),
+                        ]
                     } else {
-                        color
+                        [solid_text_color, Color::Default]
                     };
+                    if let Some(y) = row_of(hour) {
+                        let end_x = x + usable_width - 1;
+                        drawtext(draw, &label, [x, y], end_x, fg, bg);
+                        // Mark tasks that carry on past the bottom of the grid
+                        if label_width > 0 && row_of(hour + 1).is_none() {
+                            drawtext(draw, "↓", [end_x, y], end_x, fg, bg);
+                        }
+                    }
+                    x = ox;
+                    hour += 1;
+                }
+            }
+
+            // Mark the current time with a bar when editing today
+            let now = chrono::Local::now();
+            let time = Time::new(now.hour() as usize, now.minute() as usize);
+            let x = ox + time.min * max_width / 60;
+            let y = row_of(time.hour).filter(|&y| target_date == now.date_naive() && y < h);
+            if let Some(y) = y.filter(|_| x < w) {
+                let fg = match color {
+                    true => get_edit_color(config.now_color),
+                    false => solid_text_color,
+                };
+                let under = shown
+                    .iter()
+                    .chain(&carried)
+                    .find(|task| task.slot.contains(time));
+                let bg = match under {
+                    Some(task) if selected(task) => sel_color,
+                    Some(task) if color => get_edit_color(task_color(task, config.palette())),
+                    _ => Color::Default,
+                };
+                draw.set(y, x, char!('│', fg, bg));
+            }
+
+            if note_mode {
+                let notes = state
+                    .task_at(cursor)
+                    .and_then(|task| task.notes.as_deref())
+                    .unwrap_or("");
+                let header = "note (esc to close):";
+                drawtext(draw, header, [0, pane_y], w, sel_color, Color::Default);
+                for (i, line) in format!("{notes}_").lines().enumerate() {
+                    let y = pane_y + 1 + i;
+                    drawtext(draw, line, [0, y], w, solid_text_color, Color::Default);
+                }
+            }
+
+            let mut status = cursor.to_string();
+            if let Some(task) = state.task_at(cursor) {
+                let slot = &task.slot;
+                status = format!(
+                    "{status}  {}: {}-{} ({})",
+                    task.label,
+                    slot.start,
+                    slot.end(),
+                    format_duration(slot.duration)
+                );
+            }
+            let status_y = h.saturating_sub(1);
+            drawtext(draw, &status, [0, status_y], w, text_color, Color::Default);
+
+            if help_open {
+                let commands = command_help(&config.keys)
+                    .into_iter()
+                    .map(|(name, effect)| (format!("{}{name}", config.keys.command), effect));
+                let keys = key_help(&config.keys);
+                let lines = std::iter::once(("help (any key to close)".to_owned(), ""))
+                    .chain(keys)
+                    .chain(commands);
+                for (i, (key, effect)) in lines.enumerate() {
+                    let line = format!("{key: <14}{effect: <46}");
                     drawtext(
                         draw,
-                        &label,
-                        [x, y],
-                        x + usable_width - 1,
-                        Color::Rgb(240, 240, 240),
-                        color,
+                        &line,
+                        [2, oy + i],
+                        w,
+                        solid_text_color,
+                        Color::Default,
                     );
-                    x = ox;
-                    y += 1;
                 }
             }
 
             if cmd_mode {
-                const DOCS: &str = ": (s)ave | save&(q)uit | e(x)it | (d)elete";
-                drawtext(draw, DOCS, [0, 14], 99, solid_text_color, Color::Default);
+                let docs = match prompt {
+                    '/' => vec![
+                        "search labels (Enter with nothing typed for the next match)".to_owned(),
+                    ],
+                    _ => command_help(&config.keys)
+                        .iter()
+                        .map(|(name, effect)| format!("{name} {effect}"))
+                        .collect(),
+                };
+                let line = format!("{prompt}{command}_");
+                drawtext(
+                    draw,
+                    &line,
+                    [0, pane_y],
+                    w,
+                    solid_text_color,
+                    Color::Default,
+                );
+                // Wrap the docs to the width, above the status line
+                let mut lines: Vec<String> = vec![];
+                for doc in docs {
+                    match lines.last_mut() {
+                        Some(line) if line.chars().count() + 3 + doc.chars().count() <= w => {
+                            line.push_str(" | ");
+                            line.push_str(&doc);
+                        }
+                        _ => lines.push(doc),
+                    }
+                }
+                for (i, line) in lines.iter().enumerate() {
+                    let y = pane_y + 1 + i;
+                    if y >= status_y {
+                        break;
+                    }
+                    drawtext(draw, line, [0, y], w, text_color, Color::Default);
+                }
             }
         }
 
@@ -414,3 +2828,220 @@ fn edit(tomorrow: bool) -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory for one test's files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("daytape-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn failed_save_leaves_the_original_file() {
+        let dir = temp_dir("failed-save");
+        let path = dir.join("daytape.yaml");
+        std::fs::write(&path, "dates: {}\n").unwrap();
+        // The temporary file can't be created where a directory already is
+        std::fs::create_dir(dir.join("daytape.yaml.tmp")).unwrap();
+
+        let mut schedule = Schedule::default();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        schedule.dates.insert(
+            date,
+            DayState {
+                date,
+                tasks: vec![],
+            },
+        );
+        assert!(save_schedule(&path, &schedule, 0).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "dates: {}\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn load_schedule_fails_on_malformed_yaml() {
+        let dir = temp_dir("malformed");
+        let path = dir.join("daytape.yaml");
+        std::fs::write(&path, "dates: [unclosed\n").unwrap();
+        assert!(load_schedule(&path).is_err());
+
+        // Only a missing or empty file counts as an empty schedule
+        std::fs::write(&path, "").unwrap();
+        assert!(load_schedule(&path).unwrap().dates.is_empty());
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_schedule(&path).unwrap().dates.is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scrolling_follows_the_cursor_through_a_long_day() {
+        // 17 hours on a screen with room for 5
+        let [visible, total] = [5, 17];
+        assert_eq!(follow_cursor(0, 3, visible, total), 0);
+        assert_eq!(follow_cursor(0, 10, visible, total), 6);
+        assert_eq!(follow_cursor(6, 8, visible, total), 6);
+        assert_eq!(follow_cursor(6, 2, visible, total), 2);
+        assert_eq!(follow_cursor(2, 16, visible, total), 12);
+        // Whatever happened before, never past the last hour
+        assert_eq!(follow_cursor(15, 16, visible, total), 12);
+        assert_eq!(follow_cursor(4, 10, total, total), 0);
+    }
+
+    #[test]
+    fn saving_an_overlap_needs_confirming() {
+        let task = |start: &str, duration, label| {
+            let start = start.parse().unwrap();
+            Task::new(TimeSlot { start, duration }, label)
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let mut state = DayState {
+            date,
+            tasks: vec![task("09:00", 30, "Standup"), task("09:15", 45, "Dentist")],
+        };
+        assert_eq!(
+            save_warning(&state, &[]).as_deref(),
+            Some("Standup overlaps Dentist")
+        );
+
+        state.tasks[1].slot.start = Time::new(9, 30);
+        assert_eq!(save_warning(&state, &[]), None);
+
+        // Including with what's left of the day before
+        let carried = [task("00:00", 60, "Late")];
+        state.tasks.push(task("00:15", 30, "Early"));
+        assert_eq!(
+            save_warning(&state, &carried).as_deref(),
+            Some("Late overlaps Early")
+        );
+    }
+
+    #[test]
+    fn grid_fits_as_the_window_shrinks() {
+        for cells_per_hour in [4, 12, 60] {
+            // Down to the narrowest window with room for the compact grid
+            for width in (3 + cells_per_hour + 1..=200).rev() {
+                let [ox, cell_width] = grid_layout(width, cells_per_hour);
+                assert!(
+                    ox + cells_per_hour * cell_width < width,
+                    "{cells_per_hour} cells don't fit in {width} columns"
+                );
+            }
+        }
+        assert_eq!(grid_layout(80, 12), [6, 6]);
+        assert_eq!(grid_layout(18, 12), [3, 1]);
+    }
+
+    #[test]
+    fn add_rounds_late_starts_down_rather_than_wrapping() {
+        let dir = temp_dir("add-late");
+        let path = dir.join("daytape.yaml");
+        let config = Config {
+            backups: 0,
+            ..Config::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let slot = TimeSlot {
+            start: Time::new(23, 58),
+            duration: 30,
+        };
+        add(
+            &config,
+            &path,
+            "Night".into(),
+            slot,
+            Some(date),
+            false,
+            false,
+        )
+        .unwrap();
+
+        let schedule = load_schedule(&path).unwrap();
+        assert_eq!(schedule.dates[&date].tasks[0].slot.start, Time::new(23, 55));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn add_rejects_tasks_longer_than_a_day() {
+        let dir = temp_dir("add-long");
+        let path = dir.join("daytape.yaml");
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let slot = TimeSlot {
+            start: Time::new(9, 0),
+            duration: 3000,
+        };
+        let error = add(
+            &Config::default(),
+            &path,
+            "Marathon".into(),
+            slot,
+            Some(date),
+            false,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "duration 3000 is longer than a day");
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn add_checks_tasks_carried_over_from_the_day_before() {
+        let dir = temp_dir("add-carried");
+        let path = dir.join("daytape.yaml");
+        let config = Config {
+            backups: 0,
+            ..Config::default()
+        };
+        let [monday, tuesday] = [3, 4].map(|day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap());
+        let at = |hour, min, duration| TimeSlot {
+            start: Time::new(hour, min),
+            duration,
+        };
+        add(
+            &config,
+            &path,
+            "Late".into(),
+            at(23, 0, 120),
+            Some(monday),
+            false,
+            false,
+        )
+        .unwrap();
+
+        let error = add(
+            &config,
+            &path,
+            "Early".into(),
+            at(0, 15, 30),
+            Some(tuesday),
+            false,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Early overlaps Late (00:00-01:00), use --force to add it anyway"
+        );
+        add(
+            &config,
+            &path,
+            "Early".into(),
+            at(1, 0, 30),
+            Some(tuesday),
+            false,
+            false,
+        )
+        .unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}